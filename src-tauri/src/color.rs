@@ -0,0 +1,42 @@
+use image::Rgba;
+
+use crate::error::{PixoraError, Result};
+
+/// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` (leading `#` optional).
+pub fn parse_hex_color(hex: &str) -> Result<Rgba<u8>> {
+    let digits = hex.trim().trim_start_matches('#');
+    let invalid = || PixoraError::Process(format!("Invalid color: {}", hex));
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return Err(invalid()),
+    };
+
+    let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if expanded.len() == 8 { channel(6)? } else { 255 };
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_and_long_forms() {
+        assert_eq!(parse_hex_color("#fff").unwrap(), Rgba([255, 255, 255, 255]));
+        assert_eq!(parse_hex_color("0f08").unwrap(), Rgba([0, 255, 0, 136]));
+        assert_eq!(parse_hex_color(" #1a2b3c ").unwrap(), Rgba([26, 43, 60, 255]));
+        assert_eq!(parse_hex_color("#1a2b3c80").unwrap(), Rgba([26, 43, 60, 128]));
+    }
+
+    #[test]
+    fn rejects_bad_colors() {
+        for hex in ["", "#12", "#12345", "#ggg", "#1a2b3c4d5e", "#ééé"] {
+            assert!(parse_hex_color(hex).is_err(), "{}", hex);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use image::{DynamicImage, RgbaImage};
+use serde::Deserialize;
+
+/// Tonal and color corrections. Every field defaults to "no change".
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjustSettings {
+    /// Exposure compensation in stops, applied in linear light.
+    #[serde(default)]
+    pub exposure: f32,
+    /// -100 to 100; shifts all tones by up to a full step of white.
+    #[serde(default)]
+    pub brightness: f32,
+    /// -100 to 100 around mid-gray; 100 doubles the contrast.
+    #[serde(default)]
+    pub contrast: f32,
+    /// -100 (grayscale) to 100 (double saturation).
+    #[serde(default)]
+    pub saturation: f32,
+    /// Like saturation, but weighted towards muted colors.
+    #[serde(default)]
+    pub vibrance: f32,
+    /// Values above 1 lighten the midtones.
+    #[serde(default = "default_gamma")]
+    pub gamma: f32,
+    /// Stretch the histogram so the darkest and brightest tones reach black and white.
+    #[serde(default)]
+    pub auto_levels: bool,
+    /// Gray-world white balance: scale channels so the average color is neutral.
+    #[serde(default)]
+    pub auto_white_balance: bool,
+}
+
+fn default_gamma() -> f32 {
+    1.0
+}
+
+/// Share of pixels ignored at each end of the histogram by auto-levels.
+const LEVELS_CLIP: f64 = 0.005;
+/// Gray-world gains are kept in this range so single-color images survive.
+const WB_GAIN_RANGE: (f32, f32) = (0.5, 2.0);
+
+impl AdjustSettings {
+    fn is_identity(&self) -> bool {
+        self.exposure == 0.0
+            && self.brightness == 0.0
+            && self.contrast == 0.0
+            && self.saturation == 0.0
+            && self.vibrance == 0.0
+            && (self.gamma <= 0.0 || self.gamma == 1.0)
+            && !self.auto_levels
+            && !self.auto_white_balance
+    }
+}
+
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn histograms(img: &RgbaImage) -> [[u64; 256]; 3] {
+    let mut hist = [[0u64; 256]; 3];
+    for px in img.pixels().filter(|px| px[3] > 0) {
+        for c in 0..3 {
+            hist[c][px[c] as usize] += 1;
+        }
+    }
+    hist
+}
+
+fn gray_world_gains(hist: &[[u64; 256]; 3]) -> [f32; 3] {
+    let means: Vec<f64> = hist
+        .iter()
+        .map(|h| {
+            let count: u64 = h.iter().sum();
+            let total: u64 = h.iter().enumerate().map(|(v, n)| v as u64 * n).sum();
+            if count == 0 { 0.0 } else { total as f64 / count as f64 }
+        })
+        .collect();
+    let gray = (means[0] + means[1] + means[2]) / 3.0;
+    let mut gains = [1.0; 3];
+    for c in 0..3 {
+        if means[c] > 0.0 {
+            gains[c] = ((gray / means[c]) as f32).clamp(WB_GAIN_RANGE.0, WB_GAIN_RANGE.1);
+        }
+    }
+    gains
+}
+
+/// Black and white points (0–1) over all channels after `gains`.
+fn level_points(hist: &[[u64; 256]; 3], gains: [f32; 3]) -> (f32, f32) {
+    let mut combined = [0u64; 256];
+    for c in 0..3 {
+        for (v, n) in hist[c].iter().enumerate() {
+            let mapped = (v as f32 * gains[c]).round().min(255.0) as usize;
+            combined[mapped] += n;
+        }
+    }
+    let total: u64 = combined.iter().sum();
+    let clip = (total as f64 * LEVELS_CLIP) as u64;
+
+    let mut seen = 0;
+    let low = combined.iter().position(|n| { seen += n; seen > clip }).unwrap_or(0);
+    seen = 0;
+    let high = 255 - combined.iter().rev().position(|n| { seen += n; seen > clip }).unwrap_or(0);
+    if high <= low {
+        return (0.0, 1.0);
+    }
+    (low as f32 / 255.0, high as f32 / 255.0)
+}
+
+/// White balance gains and black/white points found by the automatic
+/// corrections. Measured once per animation so every frame gets the same
+/// values.
+#[derive(Clone, Copy, Debug)]
+pub struct AutoCorrection {
+    gains: [f32; 3],
+    black: f32,
+    white: f32,
+}
+
+impl Default for AutoCorrection {
+    fn default() -> Self {
+        AutoCorrection { gains: [1.0; 3], black: 0.0, white: 1.0 }
+    }
+}
+
+/// Measures the automatic corrections enabled in `s` on `img`.
+pub fn measure_auto_correction(img: &DynamicImage, s: &AdjustSettings) -> AutoCorrection {
+    if !s.auto_levels && !s.auto_white_balance {
+        return AutoCorrection::default();
+    }
+    let hist = histograms(&img.to_rgba8());
+    let gains = if s.auto_white_balance { gray_world_gains(&hist) } else { [1.0; 3] };
+    let (black, white) = if s.auto_levels { level_points(&hist, gains) } else { (0.0, 1.0) };
+    AutoCorrection { gains, black, white }
+}
+
+/// Per-channel curve combining every adjustment that acts on one channel at a time.
+fn build_luts(s: &AdjustSettings, auto: AutoCorrection) -> [[u8; 256]; 3] {
+    let AutoCorrection { gains, black, white } = auto;
+    let exposure = 2f32.powf(s.exposure);
+    let brightness = s.brightness.clamp(-100.0, 100.0) / 100.0;
+    let contrast = 1.0 + s.contrast.clamp(-100.0, 100.0) / 100.0;
+    let gamma = if s.gamma > 0.0 { 1.0 / s.gamma } else { 1.0 };
+
+    let mut luts = [[0u8; 256]; 3];
+    for (c, lut) in luts.iter_mut().enumerate() {
+        for (i, out) in lut.iter_mut().enumerate() {
+            let mut v = (i as f32 / 255.0 * gains[c]).min(1.0);
+            v = ((v - black) / (white - black)).clamp(0.0, 1.0);
+            if exposure != 1.0 {
+                v = linear_to_srgb((srgb_to_linear(v) * exposure).min(1.0));
+            }
+            v = (v + brightness).clamp(0.0, 1.0);
+            v = ((v - 0.5) * contrast + 0.5).clamp(0.0, 1.0);
+            v = v.powf(gamma);
+            *out = (v * 255.0).round() as u8;
+        }
+    }
+    luts
+}
+
+/// Applies `s` to the color channels, with the automatic corrections taken
+/// from `auto` (see `measure_auto_correction`). The manual values are the
+/// same for every image in a batch.
+pub fn apply_adjustments(img: DynamicImage, s: &AdjustSettings, auto: AutoCorrection) -> DynamicImage {
+    if s.is_identity() {
+        return img;
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    let luts = build_luts(s, auto);
+    let saturation = 1.0 + s.saturation.clamp(-100.0, 100.0) / 100.0;
+    let vibrance = s.vibrance.clamp(-100.0, 100.0) / 100.0;
+    let color = saturation != 1.0 || vibrance != 0.0;
+
+    for px in rgba.pixels_mut() {
+        let mut rgb = [0f32; 3];
+        for c in 0..3 {
+            rgb[c] = luts[c][px[c] as usize] as f32;
+        }
+        if color {
+            let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            let max = rgb[0].max(rgb[1]).max(rgb[2]);
+            let min = rgb[0].min(rgb[1]).min(rgb[2]);
+            let chroma = (max - min) / 255.0;
+            let factor = saturation * (1.0 + vibrance * (1.0 - chroma));
+            for v in rgb.iter_mut() {
+                *v = luma + (*v - luma) * factor;
+            }
+        }
+        for c in 0..3 {
+            px[c] = rgb[c].round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+}
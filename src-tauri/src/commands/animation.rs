@@ -0,0 +1,488 @@
+use color_quant::NeuQuant;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, DynamicImage, Frame, GenericImageView, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, State};
+use webp_animation::{AnimParams, Encoder as WebPAnimEncoder, EncoderOptions, EncodingConfig};
+
+use crate::error::{PixoraError, Result};
+use crate::state::PixoraState;
+use super::quantize::Indexed;
+use super::pipeline::{next_temp_path, register_temp, ProcessResult};
+use super::resample::{resample, ResampleFilter};
+use super::resize::{data_url_bytes, decode_bytes, decode_data_url, encode_image};
+
+/// Pixels sampled across all frames when building a shared GIF palette.
+const PALETTE_SAMPLE_PIXELS: usize = 1 << 20;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractFrameResult {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub frame_count: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAnimationOptions {
+    pub format: String,
+    pub delay_ms: u32,
+    /// How many times the animation plays; 0 loops forever.
+    #[serde(default)]
+    pub loop_count: u16,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub quality: Option<u8>,
+    /// GIF only: quantize all frames against one shared palette instead of one per frame.
+    #[serde(default)]
+    pub optimize_palette: bool,
+}
+
+/// Frames of an animated file and how many times it plays (0 for forever).
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    pub loop_count: u16,
+}
+
+/// Loop count of a GIF or WebP, in the terms of `encode_animation`. A GIF
+/// without a loop block plays once.
+fn source_loop_count(bytes: &[u8], format: ImageFormat) -> Result<u16> {
+    match format {
+        ImageFormat::Gif => {
+            let mut decoder = gif::DecodeOptions::new()
+                .read_info(Cursor::new(bytes))
+                .map_err(|e| PixoraError::Image(e.to_string()))?;
+            // The loop block comes before the first frame.
+            decoder.next_frame_info().map_err(|e| PixoraError::Image(e.to_string()))?;
+            Ok(match decoder.repeat() {
+                gif::Repeat::Infinite => 0,
+                gif::Repeat::Finite(n) => n.saturating_add(1),
+            })
+        }
+        _ => Ok(webp_loop_count(bytes).unwrap_or(0)),
+    }
+}
+
+/// Loop count from the ANIM chunk of an animated WebP; 0 loops forever.
+fn webp_loop_count(bytes: &[u8]) -> Option<u16> {
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let data = bytes.get(pos + 8..pos + 8 + size)?;
+        if &bytes[pos..pos + 4] == b"ANIM" {
+            return Some(u16::from_le_bytes(data.get(4..6)?.try_into().ok()?));
+        }
+        pos += 8 + size + size % 2;
+    }
+    None
+}
+
+/// Decodes every frame of an animated GIF or WebP. Returns `None` for still
+/// images, including single-frame GIFs, so callers can keep their usual path.
+///
+/// Frames come back composited onto the full canvas with disposal already
+/// applied, which keeps per-frame processing independent of the source layout.
+pub fn decode_frames(bytes: &[u8]) -> Result<Option<Animation>> {
+    let format = image::guess_format(bytes);
+    let frames = match format {
+        Ok(ImageFormat::Gif) => GifDecoder::new(Cursor::new(bytes))
+            .map_err(|e| PixoraError::Image(e.to_string()))?
+            .into_frames()
+            .collect_frames(),
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|e| PixoraError::Image(e.to_string()))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames().collect_frames()
+        }
+        _ => return Ok(None),
+    }
+    .map_err(|e| PixoraError::Image(e.to_string()))?;
+
+    if frames.len() < 2 {
+        return Ok(None);
+    }
+    let loop_count = match format {
+        Ok(format) => source_loop_count(bytes, format)?,
+        Err(_) => 0,
+    };
+    Ok(Some(Animation { frames, loop_count }))
+}
+
+pub fn can_animate(format: &str) -> bool {
+    matches!(format, "gif" | "webp")
+}
+
+/// Runs `f` over every frame, keeping each frame's delay.
+pub fn map_frames<F>(frames: Vec<Frame>, mut f: F) -> Result<Vec<Frame>>
+where
+    F: FnMut(DynamicImage) -> Result<DynamicImage>,
+{
+    frames
+        .into_iter()
+        .map(|frame| {
+            let delay = frame.delay();
+            let out = f(DynamicImage::ImageRgba8(frame.into_buffer()))?;
+            Ok(Frame::from_parts(out.to_rgba8(), 0, 0, delay))
+        })
+        .collect()
+}
+
+fn frame_delay_ms(frame: &Frame) -> u32 {
+    let (numer, denom) = frame.delay().numer_denom_ms();
+    numer.checked_div(denom).unwrap_or(0)
+}
+
+/// GIF loop block for an animation that plays `loop_count` times. GIFs
+/// store the repeats after the first play, and play once without a block.
+fn gif_repeat(loop_count: u16) -> gif::Repeat {
+    match loop_count {
+        0 => gif::Repeat::Infinite,
+        n => gif::Repeat::Finite(n - 1),
+    }
+}
+
+/// Encodes frames as an animated GIF or WebP that plays `loop_count` times,
+/// or forever for 0.
+/// WebP frames are encoded lossy at `quality`; GIF always uses a 256-color palette.
+pub fn encode_animation(frames: &[Frame], format: &str, quality: u8, loop_count: u16) -> Result<Vec<u8>> {
+    let first = frames
+        .first()
+        .ok_or_else(|| PixoraError::Process("Animation has no frames".to_string()))?;
+    let dims = first.buffer().dimensions();
+    if frames.iter().any(|f| f.buffer().dimensions() != dims) {
+        return Err(PixoraError::Process("All animation frames must have the same size".to_string()));
+    }
+
+    match format {
+        "gif" => {
+            let mut buf = Vec::new();
+            {
+                let mut enc = GifEncoder::new_with_speed(&mut buf, 10);
+                let repeat = match gif_repeat(loop_count) {
+                    gif::Repeat::Infinite => Repeat::Infinite,
+                    gif::Repeat::Finite(n) => Repeat::Finite(n),
+                };
+                enc.set_repeat(repeat).map_err(|e| PixoraError::Image(e.to_string()))?;
+                enc.encode_frames(frames.iter().cloned())
+                    .map_err(|e| PixoraError::Image(e.to_string()))?;
+            }
+            Ok(buf)
+        }
+        "webp" => {
+            let options = EncoderOptions {
+                anim_params: AnimParams { loop_count: loop_count as i32 },
+                encoding_config: Some(EncodingConfig::new_lossy(quality.clamp(1, 100) as f32)),
+                ..Default::default()
+            };
+            let mut enc = WebPAnimEncoder::new_with_options(dims, options)
+                .map_err(|e| PixoraError::Image(format!("WebP animation encoder: {}", e)))?;
+            let mut timestamp: i32 = 0;
+            for frame in frames {
+                enc.add_frame(frame.buffer().as_raw(), timestamp)
+                    .map_err(|e| PixoraError::Image(format!("WebP animation frame: {}", e)))?;
+                timestamp += frame_delay_ms(frame).max(1) as i32;
+            }
+            let data = enc
+                .finalize(timestamp)
+                .map_err(|e| PixoraError::Image(format!("WebP animation encode: {}", e)))?;
+            Ok(data.to_vec())
+        }
+        other => Err(PixoraError::Process(format!(
+            "Format {} can't store animation; use GIF or WebP, or extract a single frame",
+            other
+        ))),
+    }
+}
+
+/// Encodes frames as a GIF sharing a single global palette. Colors stay
+/// stable from frame to frame, which avoids the flicker of per-frame palettes.
+pub fn encode_gif_shared_palette(frames: &[Frame], loop_count: u16) -> Result<Vec<u8>> {
+    let first = frames
+        .first()
+        .ok_or_else(|| PixoraError::Process("Animation has no frames".to_string()))?;
+    let (width, height) = first.buffer().dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(PixoraError::Process("Image too large for GIF".to_string()));
+    }
+    if frames.iter().any(|f| f.buffer().dimensions() != (width, height)) {
+        return Err(PixoraError::Process("All animation frames must have the same size".to_string()));
+    }
+
+    // Index 255 is reserved for fully transparent pixels.
+    let total = frames.len() * (width * height) as usize;
+    let step = total.div_ceil(PALETTE_SAMPLE_PIXELS).max(1);
+    let samples: Vec<u8> = frames
+        .iter()
+        .flat_map(|f| f.buffer().pixels())
+        .step_by(step)
+        .filter(|p| p[3] > 0)
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect();
+    let samples = if samples.is_empty() { vec![0, 0, 0, 255] } else { samples };
+    let quant = NeuQuant::new(10, 255, &samples);
+
+    let mut palette = quant.color_map_rgb();
+    palette.resize(256 * 3, 0);
+
+    let mut buf = Vec::new();
+    {
+        let mut enc = gif::Encoder::new(&mut buf, width as u16, height as u16, &palette)
+            .map_err(|e| PixoraError::Image(e.to_string()))?;
+        enc.set_repeat(gif_repeat(loop_count)).map_err(|e| PixoraError::Image(e.to_string()))?;
+
+        for frame in frames {
+            let indices: Vec<u8> = frame
+                .buffer()
+                .pixels()
+                .map(|p| if p[3] < 128 { 255 } else { quant.index_of(&[p[0], p[1], p[2], 255]) as u8 })
+                .collect();
+            let gif_frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: (frame_delay_ms(frame) / 10).min(u16::MAX as u32) as u16,
+                dispose: gif::DisposalMethod::Background,
+                transparent: Some(255),
+                buffer: Cow::Owned(indices),
+                ..Default::default()
+            };
+            enc.write_frame(&gif_frame).map_err(|e| PixoraError::Image(e.to_string()))?;
+        }
+    }
+    Ok(buf)
+}
+
+/// Writes quantized frames as a GIF straight from their indices, under one
+/// global palette. Frames must come from the same palette; the transparent
+/// entry, if any frame has one, sits right after its colors.
+pub fn encode_indexed_gif(frames: &[(Indexed, Delay)], loop_count: u16) -> Result<Vec<u8>> {
+    let (first, _) = frames
+        .first()
+        .ok_or_else(|| PixoraError::Process("Animation has no frames".to_string()))?;
+    let (width, height) = (first.width, first.height);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(PixoraError::Process("Image too large for GIF".to_string()));
+    }
+    if frames.iter().any(|(f, _)| (f.width, f.height) != (width, height)) {
+        return Err(PixoraError::Process("All animation frames must have the same size".to_string()));
+    }
+
+    // Frames without transparency lack the trailing transparent entry, so
+    // the longest palette covers them all.
+    let colors = frames.iter().map(|(f, _)| &f.palette).max_by_key(|p| p.len()).unwrap_or(&first.palette);
+    let entries = colors.len().next_power_of_two().max(2);
+    let mut palette: Vec<u8> = colors.iter().flatten().copied().collect();
+    palette.resize(entries * 3, 0);
+
+    let mut buf = Vec::new();
+    {
+        let mut enc = gif::Encoder::new(&mut buf, width as u16, height as u16, &palette)
+            .map_err(|e| PixoraError::Image(e.to_string()))?;
+        enc.set_repeat(gif_repeat(loop_count)).map_err(|e| PixoraError::Image(e.to_string()))?;
+
+        for (frame, delay) in frames {
+            let (numer, denom) = delay.numer_denom_ms();
+            let gif_frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: (numer.checked_div(denom).unwrap_or(0) / 10).min(u16::MAX as u32) as u16,
+                dispose: gif::DisposalMethod::Background,
+                transparent: frame.transparent,
+                buffer: Cow::Borrowed(&frame.indices),
+                ..Default::default()
+            };
+            enc.write_frame(&gif_frame).map_err(|e| PixoraError::Image(e.to_string()))?;
+        }
+    }
+    Ok(buf)
+}
+
+/// Scales `img` to fit inside `width`×`height` and centers it on a transparent canvas.
+fn fit_to_canvas(img: &DynamicImage, width: u32, height: u32) -> Result<RgbaImage> {
+    let (w, h) = img.dimensions();
+    let ratio = (width as f64 / w as f64).min(height as f64 / h as f64);
+    let nw = ((w as f64 * ratio).round() as u32).clamp(1, width);
+    let nh = ((h as f64 * ratio).round() as u32).clamp(1, height);
+    let scaled = if (nw, nh) == (w, h) {
+        img.to_rgba8()
+    } else {
+        resample(img, nw, nh, ResampleFilter::default())?.to_rgba8()
+    };
+
+    let mut canvas = RgbaImage::new(width, height);
+    let x = ((width - nw) / 2) as i64;
+    let y = ((height - nh) / 2) as i64;
+    image::imageops::overlay(&mut canvas, &scaled, x, y);
+    Ok(canvas)
+}
+
+fn build_animation(app: &AppHandle, data_urls: &[String], options: &CreateAnimationOptions) -> Result<ProcessResult> {
+    if !can_animate(&options.format) {
+        return Err(PixoraError::Process(format!("Format {} can't store animation; use GIF or WebP", options.format)));
+    }
+    if data_urls.len() < 2 {
+        return Err(PixoraError::Process("An animation needs at least two images".to_string()));
+    }
+
+    let delay = Delay::from_numer_denom_ms(options.delay_ms.max(10), 1);
+    let total = data_urls.len();
+    let mut canvas_size = None;
+    let mut frames = Vec::with_capacity(total);
+
+    for (i, data_url) in data_urls.iter().enumerate() {
+        let progress = (i as f32 / total as f32 * 100.0) as u32;
+        let _ = app.emit("animation-progress", progress);
+
+        let (img, _) = decode_data_url(data_url)?;
+        let (width, height) = *canvas_size.get_or_insert_with(|| {
+            let (w, h) = img.dimensions();
+            (options.width.unwrap_or(w), options.height.unwrap_or(h))
+        });
+        frames.push(Frame::from_parts(fit_to_canvas(&img, width.max(1), height.max(1))?, 0, 0, delay));
+    }
+
+    let quality = options.quality.unwrap_or(85).clamp(1, 100);
+    let encoded = if options.format == "gif" && options.optimize_palette {
+        encode_gif_shared_palette(&frames, options.loop_count)?
+    } else {
+        encode_animation(&frames, &options.format, quality, options.loop_count)?
+    };
+
+    let out_path = next_temp_path(app, &options.format)?;
+    std::fs::write(&out_path, encoded)?;
+    let _ = app.emit("animation-progress", 100);
+
+    let (width, height) = frames[0].buffer().dimensions();
+    Ok(ProcessResult {
+        output_path: out_path.to_string_lossy().into_owned(),
+        width,
+        height,
+        size_bytes: out_path.metadata()?.len(),
+        frame_count: frames.len() as u32,
+        trim: None,
+        smart_crop: None,
+        size_without_denoise: None,
+        deskew_angle: None,
+    })
+}
+
+/// Picks frame `index` out of an animation.
+pub fn pick_frame(frames: Vec<Frame>, index: usize) -> Result<DynamicImage> {
+    let count = frames.len();
+    frames
+        .into_iter()
+        .nth(index)
+        .map(|f| DynamicImage::ImageRgba8(f.into_buffer()))
+        .ok_or_else(|| PixoraError::Process(format!("Frame {} out of range (0..{})", index, count)))
+}
+
+#[tauri::command]
+pub async fn extract_frame(data_url: String, index: usize) -> Result<ExtractFrameResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (bytes, format) = data_url_bytes(&data_url)?;
+        let (img, frame_count) = match decode_frames(&bytes)? {
+            Some(animation) => {
+                let count = animation.frames.len() as u32;
+                (pick_frame(animation.frames, index)?, count)
+            }
+            None if index == 0 => (decode_bytes(&bytes, &format)?, 1),
+            None => {
+                return Err(PixoraError::Process(format!("Frame {} out of range (0..1)", index)))
+            }
+        };
+
+        let (data_url, _) = encode_image(&img, "png", 100)?;
+        Ok(ExtractFrameResult {
+            data_url,
+            width: img.width(),
+            height: img.height(),
+            frame_count,
+        })
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+/// Builds an animated GIF or WebP from an ordered list of images.
+#[tauri::command]
+pub async fn create_animation(
+    app: AppHandle,
+    state: State<'_, PixoraState>,
+    data_urls: Vec<String>,
+    options: CreateAnimationOptions,
+) -> Result<ProcessResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || build_animation(&app, &data_urls, &options))
+        .await
+        .map_err(|e| PixoraError::Process(e.to_string()))??;
+
+    register_temp(&state, PathBuf::from(&result.output_path))?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Delay, RgbaImage};
+
+    fn frames() -> Vec<Frame> {
+        [[255, 0, 0, 255], [0, 0, 255, 255]]
+            .into_iter()
+            .map(|c| {
+                let delay = Delay::from_numer_denom_ms(100, 1);
+                Frame::from_parts(RgbaImage::from_pixel(4, 3, image::Rgba(c)), 0, 0, delay)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_loop_count() {
+        for format in ["gif", "webp"] {
+            for loop_count in [0, 1, 3] {
+                let bytes = encode_animation(&frames(), format, 80, loop_count).unwrap();
+                let animation = decode_frames(&bytes).unwrap().unwrap();
+                assert_eq!(animation.frames.len(), 2);
+                assert_eq!(animation.loop_count, loop_count, "{} {}", format, loop_count);
+            }
+        }
+        let bytes = encode_gif_shared_palette(&frames(), 2).unwrap();
+        assert_eq!(decode_frames(&bytes).unwrap().unwrap().loop_count, 2);
+    }
+
+    #[test]
+    fn writes_indexed_frames_as_they_are() {
+        let palette = vec![[255, 0, 0], [0, 0, 255]];
+        let indexed = |indices: Vec<u8>, transparent| Indexed {
+            width: 2,
+            height: 1,
+            palette: palette.clone(),
+            indices,
+            transparent,
+        };
+        let mut with_clear = indexed(vec![2, 1], Some(2));
+        with_clear.palette.push([0, 0, 0]);
+        let delay = Delay::from_numer_denom_ms(50, 1);
+        let bytes = encode_indexed_gif(&[(indexed(vec![0, 1], None), delay), (with_clear, delay)], 0).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new().read_info(Cursor::new(&bytes)).unwrap();
+        assert_eq!(decoder.global_palette().unwrap().len(), 4 * 3);
+        let first = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!((first.buffer.to_vec(), first.delay, first.palette.is_none()), (vec![0, 1], 5, true));
+        let second = decoder.read_next_frame().unwrap().unwrap();
+        assert_eq!((second.buffer.to_vec(), second.transparent), (vec![2, 1], Some(2)));
+    }
+
+    #[test]
+    fn still_images_have_no_animation() {
+        let mut buf = Cursor::new(Vec::new());
+        RgbaImage::new(2, 2).write_to(&mut buf, ImageFormat::Gif).unwrap();
+        assert!(decode_frames(buf.get_ref()).unwrap().is_none());
+        assert_eq!(webp_loop_count(b"RIFF\0\0\0\0WEBP"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::resize::parse_background;
+use super::transform::Anchor;
+
+/// Largest canvas side we are willing to allocate.
+const MAX_SIDE: u32 = 16384;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum CanvasPad {
+    /// Canvas of exactly this size with the image placed by `anchor`. An
+    /// image larger than the canvas is cropped at the same anchor.
+    Size {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        anchor: Anchor,
+    },
+    /// Extra pixels on each side.
+    Margins {
+        #[serde(default)]
+        top: u32,
+        #[serde(default)]
+        right: u32,
+        #[serde(default)]
+        bottom: u32,
+        #[serde(default)]
+        left: u32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CanvasSettings {
+    /// The padded and bordered canvas may be at most 16384 px per side.
+    #[serde(default)]
+    pub pad: Option<CanvasPad>,
+    /// Hex fill for the padding; transparent when unset.
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Border drawn around the outside of the (padded) image, in pixels.
+    #[serde(default)]
+    pub border_width: u32,
+    /// Hex border color, black by default.
+    #[serde(default)]
+    pub border_color: Option<String>,
+    /// Rounds the corners, in pixels.
+    #[serde(default)]
+    pub corner_radius: f32,
+    /// Center-crops to a square and masks it to a circle.
+    #[serde(default)]
+    pub circle: bool,
+}
+
+impl CanvasSettings {
+    /// Whether the step can leave transparent pixels, so the output needs a
+    /// format with alpha.
+    pub fn introduces_transparency(&self) -> Result<bool> {
+        if self.circle || self.corner_radius > 0.0 {
+            return Ok(true);
+        }
+        if self.pad.is_some() && parse_background(self.background.as_deref())?[3] < 255 {
+            return Ok(true);
+        }
+        if self.border_width > 0 {
+            if let Some(hex) = self.border_color.as_deref() {
+                return Ok(parse_hex_color(hex)?[3] < 255);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Sum of `parts` as one canvas side, or an error past `MAX_SIDE`.
+fn canvas_side(parts: &[u32]) -> Result<u32> {
+    parts
+        .iter()
+        .try_fold(0u32, |sum, &p| sum.checked_add(p))
+        .filter(|&side| side <= MAX_SIDE)
+        .ok_or_else(|| PixoraError::Process(format!("Canvas exceeds the {} px limit", MAX_SIDE)))
+}
+
+fn pad(img: &RgbaImage, pad: CanvasPad, fill: Rgba<u8>) -> Result<RgbaImage> {
+    let (w, h) = img.dimensions();
+    Ok(match pad {
+        CanvasPad::Size { width, height, anchor } => {
+            let (width, height) = (canvas_side(&[width.max(1)])?, canvas_side(&[height.max(1)])?);
+            let (cw, ch) = (w.min(width), h.min(height));
+            let (cx, cy) = anchor.offset(w - cw, h - ch);
+            let content = imageops::crop_imm(img, cx, cy, cw, ch).to_image();
+            let (x, y) = anchor.offset(width - cw, height - ch);
+            let mut canvas = RgbaImage::from_pixel(width, height, fill);
+            imageops::replace(&mut canvas, &content, x as i64, y as i64);
+            canvas
+        }
+        CanvasPad::Margins { top, right, bottom, left } => {
+            let (width, height) = (canvas_side(&[w, left, right])?, canvas_side(&[h, top, bottom])?);
+            let mut canvas = RgbaImage::from_pixel(width, height, fill);
+            imageops::replace(&mut canvas, img, left as i64, top as i64);
+            canvas
+        }
+    })
+}
+
+/// Distance from the pixel center at (`x`, `y`) to the edge of a
+/// `width`×`height` rounded rectangle; positive inside.
+fn inside_distance(x: u32, y: u32, width: f32, height: f32, radius: f32) -> f32 {
+    let (hw, hh) = (width / 2.0, height / 2.0);
+    let qx = (x as f32 + 0.5 - hw).abs() - (hw - radius);
+    let qy = (y as f32 + 0.5 - hh).abs() - (hh - radius);
+    let outside = qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0);
+    radius - outside
+}
+
+/// Border band and anti-aliased corner mask in one pass.
+fn border_and_mask(img: &mut RgbaImage, border: u32, color: Rgba<u8>, radius: f32) {
+    let (w, h) = img.dimensions();
+    let radius = radius.clamp(0.0, w.min(h) as f32 / 2.0);
+    let border = border as f32;
+
+    for (x, y, px) in img.enumerate_pixels_mut() {
+        let d = inside_distance(x, y, w as f32, h as f32, radius);
+        if border > 0.0 {
+            let mix = (border - d + 0.5).clamp(0.0, 1.0);
+            if mix > 0.0 {
+                for c in 0..4 {
+                    px[c] = (px[c] as f32 + (color[c] as f32 - px[c] as f32) * mix).round() as u8;
+                }
+            }
+        }
+        let coverage = (d + 0.5).clamp(0.0, 1.0);
+        if coverage < 1.0 {
+            px[3] = (px[3] as f32 * coverage).round() as u8;
+        }
+    }
+}
+
+/// Padding, border and corner masking, in that order. The border sits
+/// outside the padded image and follows the rounded corners.
+pub fn apply_canvas(img: DynamicImage, settings: &CanvasSettings) -> Result<DynamicImage> {
+    let had_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+
+    if let Some(p) = settings.pad {
+        rgba = pad(&rgba, p, parse_background(settings.background.as_deref())?)?;
+    }
+
+    if settings.circle {
+        let (w, h) = rgba.dimensions();
+        let side = w.min(h);
+        if (w, h) != (side, side) {
+            rgba = imageops::crop_imm(&rgba, (w - side) / 2, (h - side) / 2, side, side).to_image();
+        }
+    }
+
+    let border = settings.border_width;
+    if border > 0 {
+        let color = match settings.border_color.as_deref() {
+            Some(hex) => parse_hex_color(hex)?,
+            None => Rgba([0, 0, 0, 255]),
+        };
+        let (w, h) = rgba.dimensions();
+        let (fw, fh) = (canvas_side(&[w, border, border])?, canvas_side(&[h, border, border])?);
+        let mut framed = RgbaImage::from_pixel(fw, fh, color);
+        imageops::replace(&mut framed, &rgba, border as i64, border as i64);
+        rgba = framed;
+        let radius = if settings.circle { rgba.width() as f32 / 2.0 } else { settings.corner_radius.max(0.0) };
+        border_and_mask(&mut rgba, border, color, radius);
+    } else if settings.circle || settings.corner_radius > 0.0 {
+        let radius = if settings.circle { rgba.width() as f32 / 2.0 } else { settings.corner_radius };
+        border_and_mask(&mut rgba, 0, Rgba([0, 0, 0, 0]), radius);
+    }
+
+    Ok(if had_alpha || settings.introduces_transparency()? {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas_side_checks_the_limit() {
+        assert_eq!(canvas_side(&[100, 20, 30]).unwrap(), 150);
+        assert_eq!(canvas_side(&[MAX_SIDE]).unwrap(), MAX_SIDE);
+        assert!(canvas_side(&[MAX_SIDE, 1]).is_err());
+        assert!(canvas_side(&[100, u32::MAX]).is_err());
+    }
+
+    #[test]
+    fn pads_margins_and_sizes() {
+        let fill = Rgba([0, 0, 0, 0]);
+        let img = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+
+        let margins = CanvasPad::Margins { top: 1, right: 2, bottom: 3, left: 4 };
+        let out = pad(&img, margins, fill).unwrap();
+        assert_eq!(out.dimensions(), (10, 6));
+        assert_eq!((out.get_pixel(4, 1)[0], out.get_pixel(3, 1)[3]), (255, 0));
+
+        // A canvas smaller than the image crops it at the anchor.
+        let size = CanvasPad::Size { width: 2, height: 4, anchor: Anchor::TopRight };
+        let out = pad(&img, size, fill).unwrap();
+        assert_eq!(out.dimensions(), (2, 4));
+        assert_eq!((out.get_pixel(1, 1)[3], out.get_pixel(1, 2)[3]), (255, 0));
+
+        let huge = CanvasPad::Margins { top: 0, right: u32::MAX, bottom: 0, left: 0 };
+        assert!(pad(&img, huge, fill).is_err());
+    }
+}
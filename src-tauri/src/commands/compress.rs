@@ -4,10 +4,18 @@ use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 use crate::error::{PixoraError, Result};
 
+use super::dpi::{read_dpi, CM_PER_INCH};
+use super::jxl::{check_jxl_quality, encode_jxl};
+use super::raw::is_raw_header;
+use super::resize::{data_url_bytes, decode_bytes};
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CompressOptions {
-    pub quality: u8,
+    /// JPEG quality, 85 when unset. Must be unset for JPEG XL, which is
+    /// always lossless.
+    #[serde(default)]
+    pub quality: Option<u8>,
     pub format: Option<String>,
 }
 
@@ -27,29 +35,18 @@ pub struct ImageInfo {
     pub height: u32,
     pub size_bytes: usize,
     pub format: String,
+    /// Resolution stored in the file, if any.
+    pub dpi: Option<f32>,
+    /// Print size at `dpi`, in centimeters.
+    pub physical_width_cm: Option<f32>,
+    pub physical_height_cm: Option<f32>,
 }
 
 fn decode(data_url: &str) -> Result<(DynamicImage, String, usize)> {
-    let (header, data) = data_url
-        .split_once(',')
-        .ok_or_else(|| PixoraError::Process("URL inválida".to_string()))?;
-
-    let format = if header.contains("jpeg") || header.contains("jpg") {
-        "jpeg"
-    } else if header.contains("png") {
-        "png"
-    } else if header.contains("webp") {
-        "webp"
-    } else {
-        "jpeg"
-    };
-
-    let bytes = general_purpose::STANDARD
-        .decode(data)
-        .map_err(|e| PixoraError::Process(e.to_string()))?;
+    let (bytes, format) = data_url_bytes(data_url)?;
     let original_size = bytes.len();
-    let img = image::load_from_memory(&bytes).map_err(|e| PixoraError::Image(e.to_string()))?;
-    Ok((img, format.to_string(), original_size))
+    let img = decode_bytes(&bytes, &format)?;
+    Ok((img, format, original_size))
 }
 
 #[tauri::command]
@@ -57,7 +54,8 @@ pub async fn compress_image(data_url: String, options: CompressOptions) -> Resul
     tauri::async_runtime::spawn_blocking(move || {
         let (img, orig_format, original_size) = decode(&data_url)?;
         let format = options.format.as_deref().unwrap_or(&orig_format).to_string();
-        let quality = options.quality.clamp(1, 100);
+        check_jxl_quality(&format, options.quality)?;
+        let quality = options.quality.unwrap_or(85).clamp(1, 100);
 
         let mut buf = Cursor::new(Vec::new());
 
@@ -68,6 +66,7 @@ pub async fn compress_image(data_url: String, options: CompressOptions) -> Resul
             "webp" => img
                 .write_to(&mut buf, ImageFormat::WebP)
                 .map_err(|e| PixoraError::Image(e.to_string()))?,
+            "jxl" => buf.get_mut().extend(encode_jxl(&img)?),
             _ => {
                 let mut encoder =
                     image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality);
@@ -87,6 +86,7 @@ pub async fn compress_image(data_url: String, options: CompressOptions) -> Resul
         let mime = match format.as_str() {
             "png" => "image/png",
             "webp" => "image/webp",
+            "jxl" => "image/jxl",
             _ => "image/jpeg",
         };
 
@@ -102,32 +102,39 @@ pub async fn compress_image(data_url: String, options: CompressOptions) -> Resul
 #[tauri::command]
 pub async fn get_image_info(data_url: String) -> Result<ImageInfo> {
     tauri::async_runtime::spawn_blocking(move || {
-        let (header, data) = data_url
+        let (header, _) = data_url
             .split_once(',')
             .ok_or_else(|| PixoraError::Process("URL inválida".to_string()))?;
 
-        let format = if header.contains("jpeg") || header.contains("jpg") {
+        let format = if is_raw_header(header) {
+            "RAW"
+        } else if header.contains("jpeg") || header.contains("jpg") {
             "JPEG"
         } else if header.contains("png") {
             "PNG"
         } else if header.contains("webp") {
             "WebP"
+        } else if header.contains("jxl") {
+            "JPEG XL"
         } else {
             "Unknown"
         };
 
-        let bytes = general_purpose::STANDARD
-            .decode(data)
-            .map_err(|e| PixoraError::Process(e.to_string()))?;
+        let (bytes, source_format) = data_url_bytes(&data_url)?;
         let size_bytes = bytes.len();
-        let img = image::load_from_memory(&bytes).map_err(|e| PixoraError::Image(e.to_string()))?;
+        let img = decode_bytes(&bytes, &source_format)?;
         let (width, height) = img.dimensions();
+        let dpi = read_dpi(&bytes);
+        let physical_cm = |px: u32| dpi.map(|d| px as f32 / d * CM_PER_INCH);
 
         Ok(ImageInfo {
             width,
             height,
             size_bytes,
             format: format.to_string(),
+            dpi,
+            physical_width_cm: physical_cm(width),
+            physical_height_cm: physical_cm(height),
         })
     }).await.map_err(|e| PixoraError::Process(e.to_string()))?
 }
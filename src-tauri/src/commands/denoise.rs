@@ -0,0 +1,187 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum DenoiseMethod {
+    /// Edge-preserving blur over a small window; fast.
+    #[default]
+    Bilateral,
+    /// Averages pixels whose surrounding patches look alike; keeps more
+    /// texture but takes several times longer.
+    NonLocalMeans,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DenoiseSettings {
+    #[serde(default)]
+    pub method: DenoiseMethod,
+    /// 0–100; grain in brightness.
+    #[serde(default = "default_luma")]
+    pub luma: f32,
+    /// 0–100; color speckles, which can be smoothed much harder.
+    #[serde(default = "default_chroma")]
+    pub chroma: f32,
+    /// Also run the steps without denoising and report that encoded size, to
+    /// show the saving.
+    #[serde(default)]
+    pub report_size: bool,
+}
+
+fn default_luma() -> f32 {
+    30.0
+}
+
+fn default_chroma() -> f32 {
+    50.0
+}
+
+/// Noise standard deviation (0–255 levels) assumed at strength 100.
+const MAX_SIGMA: f32 = 25.0;
+const BILATERAL_RADIUS: i64 = 2;
+const BILATERAL_SPATIAL_SIGMA: f32 = 1.5;
+const NLM_SEARCH_RADIUS: i64 = 3;
+const NLM_PATCH_RADIUS: i64 = 1;
+
+/// One channel as floats, for filtering.
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl Plane {
+    fn at(&self, x: i64, y: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let y = y.clamp(0, self.height as i64 - 1) as usize;
+        self.data[y * self.width + x]
+    }
+}
+
+/// Full-range BT.601 YCbCr, as used by JPEG.
+fn split_ycbcr(rgba: &RgbaImage) -> [Plane; 3] {
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let mut planes: [Plane; 3] = std::array::from_fn(|_| Plane { width, height, data: Vec::with_capacity(width * height) });
+    for px in rgba.pixels() {
+        let [r, g, b] = [px[0] as f32, px[1] as f32, px[2] as f32];
+        planes[0].data.push(0.299 * r + 0.587 * g + 0.114 * b);
+        planes[1].data.push(128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b);
+        planes[2].data.push(128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b);
+    }
+    planes
+}
+
+fn merge_ycbcr(planes: &[Plane; 3], rgba: &mut RgbaImage) {
+    for (i, px) in rgba.pixels_mut().enumerate() {
+        let (y, cb, cr) = (planes[0].data[i], planes[1].data[i] - 128.0, planes[2].data[i] - 128.0);
+        let rgb = [y + 1.402 * cr, y - 0.344136 * cb - 0.714136 * cr, y + 1.772 * cb];
+        *px = Rgba([
+            rgb[0].round().clamp(0.0, 255.0) as u8,
+            rgb[1].round().clamp(0.0, 255.0) as u8,
+            rgb[2].round().clamp(0.0, 255.0) as u8,
+            px[3],
+        ]);
+    }
+}
+
+fn bilateral(plane: &Plane, sigma: f32) -> Vec<f32> {
+    let range: Vec<f32> = (0..256).map(|d| (-((d * d) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let r = BILATERAL_RADIUS;
+    let spatial: Vec<f32> = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| (-((dx * dx + dy * dy) as f32) / (2.0 * BILATERAL_SPATIAL_SIGMA.powi(2))).exp())
+        .collect();
+
+    let mut out = Vec::with_capacity(plane.data.len());
+    for y in 0..plane.height as i64 {
+        for x in 0..plane.width as i64 {
+            let center = plane.at(x, y);
+            let (mut sum, mut weight) = (0.0, 0.0);
+            let mut k = 0;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let v = plane.at(x + dx, y + dy);
+                    let w = spatial[k] * range[((v - center).abs() as usize).min(255)];
+                    sum += v * w;
+                    weight += w;
+                    k += 1;
+                }
+            }
+            out.push(sum / weight);
+        }
+    }
+    out
+}
+
+/// Non-local means by offsets: for each shift in the search window, patch
+/// distances for every pixel come from one squared-difference image and a
+/// summed-area table.
+fn non_local_means(plane: &Plane, sigma: f32) -> Vec<f32> {
+    let (w, h) = (plane.width, plane.height);
+    let n = w * h;
+    let patch = NLM_PATCH_RADIUS;
+    let h2 = sigma * sigma;
+    let mut acc = vec![0.0f32; n];
+    let mut weights = vec![0.0f32; n];
+    let mut table = vec![0.0f64; (w + 1) * (h + 1)];
+
+    for oy in -NLM_SEARCH_RADIUS..=NLM_SEARCH_RADIUS {
+        for ox in -NLM_SEARCH_RADIUS..=NLM_SEARCH_RADIUS {
+            for y in 0..h {
+                let mut row = 0.0f64;
+                for x in 0..w {
+                    let d = plane.data[y * w + x] - plane.at(x as i64 + ox, y as i64 + oy);
+                    row += (d * d) as f64;
+                    table[(y + 1) * (w + 1) + x + 1] = table[y * (w + 1) + x + 1] + row;
+                }
+            }
+            for y in 0..h as i64 {
+                let (y0, y1) = ((y - patch).max(0) as usize, ((y + patch + 1) as usize).min(h));
+                for x in 0..w as i64 {
+                    let (x0, x1) = ((x - patch).max(0) as usize, ((x + patch + 1) as usize).min(w));
+                    let ssd = table[y1 * (w + 1) + x1] - table[y0 * (w + 1) + x1] - table[y1 * (w + 1) + x0]
+                        + table[y0 * (w + 1) + x0];
+                    let area = ((y1 - y0) * (x1 - x0)) as f32;
+                    // Patches that differ only by noise score about 2σ², so
+                    // that much distance is forgiven.
+                    let distance = (ssd as f32 / area - 2.0 * h2).max(0.0);
+                    let weight = (-distance / h2).exp();
+                    let i = y as usize * w + x as usize;
+                    acc[i] += weight * plane.at(x + ox, y + oy);
+                    weights[i] += weight;
+                }
+            }
+        }
+    }
+    acc.iter().zip(&weights).map(|(a, w)| a / w).collect()
+}
+
+/// Denoises brightness and color separately in YCbCr; alpha is untouched.
+pub fn apply_denoise(img: DynamicImage, settings: &DenoiseSettings) -> DynamicImage {
+    let strengths = [settings.luma, settings.chroma, settings.chroma].map(|s| s.clamp(0.0, 100.0) / 100.0);
+    if strengths.iter().all(|&s| s == 0.0) {
+        return img;
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    let mut planes = split_ycbcr(&rgba);
+    for (plane, strength) in planes.iter_mut().zip(strengths) {
+        if strength == 0.0 {
+            continue;
+        }
+        let sigma = strength * MAX_SIGMA;
+        plane.data = match settings.method {
+            DenoiseMethod::Bilateral => bilateral(plane, sigma),
+            DenoiseMethod::NonLocalMeans => non_local_means(plane, sigma),
+        };
+    }
+    merge_ycbcr(&planes, &mut rgba);
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+}
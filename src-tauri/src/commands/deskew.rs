@@ -0,0 +1,184 @@
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView};
+use imageproc::gradients::{horizontal_sobel, vertical_sobel};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PixoraError, Result};
+use super::jxl::check_jxl_quality;
+use super::resize::{decode_data_url, encode_image};
+use super::transform::{apply_rotate, RotateSettings};
+
+/// Longest side of the copy the angle is measured on.
+const WORK_PX: u32 = 1024;
+/// Share of the strongest near-horizontal edges that vote.
+const EDGE_SHARE: f64 = 0.05;
+const COARSE_STEP: f32 = 0.5;
+const FINE_STEP: f32 = 0.05;
+/// Smaller corrections are not worth resampling the image for.
+const MIN_CORRECTION: f32 = 0.05;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeskewSettings {
+    /// Largest tilt to look for, in degrees either way.
+    #[serde(default = "default_max_angle")]
+    pub max_angle: f32,
+    /// Crop to the largest rectangle of the original aspect ratio that has
+    /// no rotated-in corners.
+    #[serde(default)]
+    pub auto_crop: bool,
+    /// Hex color for the corners when not cropping; transparent when unset.
+    #[serde(default)]
+    pub fill: Option<String>,
+}
+
+fn default_max_angle() -> f32 {
+    10.0
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeskewResult {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+    /// Detected tilt in degrees; positive means the content leaned clockwise.
+    pub angle: f32,
+}
+
+/// Hough-style vote: edge points projected across lines at `degrees`,
+/// scored by how sharply they pile up into rows.
+fn projection_score(points: &[(f32, f32)], degrees: f32, span: usize) -> f64 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let mut bins = vec![0u32; 2 * span + 1];
+    for &(x, y) in points {
+        let row = (y * cos - x * sin).round() as i64 + span as i64;
+        if let Some(bin) = bins.get_mut(row as usize) {
+            *bin += 1;
+        }
+    }
+    bins.iter().map(|&n| (n as f64) * (n as f64)).sum()
+}
+
+fn best_angle(points: &[(f32, f32)], from: f32, to: f32, step: f32, span: usize) -> f32 {
+    let steps = ((to - from) / step).round() as i32;
+    (0..=steps)
+        .map(|i| from + i as f32 * step)
+        .map(|a| (a, projection_score(points, a, span)))
+        // Prefer the smaller correction on ties.
+        .max_by(|(a1, s1), (a2, s2)| s1.total_cmp(s2).then(a2.abs().total_cmp(&a1.abs())))
+        .map(|(a, _)| a)
+        .unwrap_or(0.0)
+}
+
+/// Estimates the tilt of the dominant horizontal structure (text lines,
+/// horizons, table rules) within ±`max_angle` degrees.
+pub fn detect_skew(img: &DynamicImage, max_angle: f32) -> f32 {
+    let max_angle = max_angle.clamp(0.0, 45.0);
+    if max_angle == 0.0 {
+        return 0.0;
+    }
+    let (width, height) = img.dimensions();
+    let scale = (WORK_PX as f32 / width.max(height) as f32).min(1.0);
+    let gray = img.to_luma8();
+    let gray = if scale < 1.0 {
+        let w = ((width as f32 * scale).round() as u32).max(1);
+        let h = ((height as f32 * scale).round() as u32).max(1);
+        imageops::resize(&gray, w, h, FilterType::Triangle)
+    } else {
+        gray
+    };
+
+    // Edges of near-horizontal structures have a mostly vertical gradient.
+    let gx = horizontal_sobel(&gray);
+    let gy = vertical_sobel(&gray);
+    let mut candidates: Vec<(u32, u32, u32)> = gx
+        .enumerate_pixels()
+        .filter_map(|(x, y, px)| {
+            let (dx, dy) = (px[0].unsigned_abs() as u32, gy.get_pixel(x, y)[0].unsigned_abs() as u32);
+            (dy > 2 * dx && dy > 0).then_some((x, y, dy))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return 0.0;
+    }
+    let keep = ((gray.width() * gray.height()) as f64 * EDGE_SHARE).ceil() as usize;
+    if candidates.len() > keep {
+        candidates.select_nth_unstable_by(keep, |a, b| b.2.cmp(&a.2));
+        candidates.truncate(keep);
+    }
+
+    let (cx, cy) = (gray.width() as f32 / 2.0, gray.height() as f32 / 2.0);
+    let points: Vec<(f32, f32)> = candidates.iter().map(|&(x, y, _)| (x as f32 - cx, y as f32 - cy)).collect();
+    let span = (gray.width() + gray.height()) as usize;
+
+    let coarse = best_angle(&points, -max_angle, max_angle, COARSE_STEP, span);
+    let fine = best_angle(
+        &points,
+        (coarse - COARSE_STEP).max(-max_angle),
+        (coarse + COARSE_STEP).min(max_angle),
+        FINE_STEP,
+        span,
+    );
+    (fine / FINE_STEP).round() * FINE_STEP
+}
+
+/// Largest centered rectangle with the aspect of `width`×`height` that
+/// stays inside the image after rotating it by `degrees`.
+fn inner_rect(width: u32, height: u32, degrees: f32) -> (u32, u32, u32, u32) {
+    let (sin, cos) = degrees.to_radians().abs().sin_cos();
+    let (w, h) = (width as f32, height as f32);
+    let scale = (w / (w * cos + h * sin)).min(h / (w * sin + h * cos));
+    let cw = ((w * scale).floor() as u32).clamp(1, width);
+    let ch = ((h * scale).floor() as u32).clamp(1, height);
+    ((width - cw) / 2, (height - ch) / 2, cw, ch)
+}
+
+/// Rotates `img` to undo a tilt of `angle` degrees, as measured by
+/// `detect_skew`.
+pub fn apply_deskew_angle(img: DynamicImage, angle: f32, settings: &DeskewSettings) -> Result<DynamicImage> {
+    if angle.abs() < MIN_CORRECTION {
+        return Ok(img);
+    }
+
+    let (width, height) = img.dimensions();
+    let rotate = RotateSettings { degrees: -angle, expand: false, fill: settings.fill.clone() };
+    let rotated = apply_rotate(img, &rotate)?;
+    Ok(if settings.auto_crop {
+        let (x, y, w, h) = inner_rect(width, height, angle);
+        rotated.crop_imm(x, y, w, h)
+    } else {
+        rotated
+    })
+}
+
+/// Straightens `img`, returning it with the detected tilt.
+pub fn apply_deskew(img: DynamicImage, settings: &DeskewSettings) -> Result<(DynamicImage, f32)> {
+    let angle = detect_skew(&img, settings.max_angle);
+    Ok((apply_deskew_angle(img, angle, settings)?, angle))
+}
+
+#[tauri::command]
+pub async fn deskew_image(
+    data_url: String,
+    deskew: DeskewSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<DeskewResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (img, orig_format) = decode_data_url(&data_url)?;
+        let format = format.unwrap_or(orig_format);
+        check_jxl_quality(&format, quality)?;
+        let quality = quality.unwrap_or(85).clamp(1, 100);
+
+        let (out, angle) = apply_deskew(img, &deskew)?;
+        let (data_url, size_bytes) = encode_image(&out, &format, quality)?;
+        Ok(DeskewResult {
+            data_url,
+            width: out.width(),
+            height: out.height(),
+            size_bytes,
+            angle,
+        })
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
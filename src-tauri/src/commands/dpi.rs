@@ -0,0 +1,138 @@
+use kamadak_exif::experimental::Writer;
+use kamadak_exif::{Field, In, Rational, Tag, Value};
+use std::io::Cursor;
+
+use crate::error::{PixoraError, Result};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const INCHES_PER_METER: f32 = 39.370_08;
+pub const CM_PER_INCH: f32 = 2.54;
+
+/// Minimal EXIF block (TIFF structure, no "Exif" prefix) holding only the
+/// resolution tags, as expected by the encoders' `set_exif_metadata`.
+pub fn resolution_exif(dpi: f32) -> Result<Vec<u8>> {
+    let rational = Rational { num: (dpi * 100.0).round() as u32, denom: 100 };
+    let x = Field { tag: Tag::XResolution, ifd_num: In::PRIMARY, value: Value::Rational(vec![rational]) };
+    let y = Field { tag: Tag::YResolution, ifd_num: In::PRIMARY, value: Value::Rational(vec![rational]) };
+    let unit = Field { tag: Tag::ResolutionUnit, ifd_num: In::PRIMARY, value: Value::Short(vec![2]) };
+
+    let mut writer = Writer::new();
+    writer.push_field(&x);
+    writer.push_field(&y);
+    writer.push_field(&unit);
+    let mut buf = Cursor::new(Vec::new());
+    writer.write(&mut buf, false).map_err(|e| PixoraError::Image(e.to_string()))?;
+    Ok(buf.into_inner())
+}
+
+/// Inserts a pHYs chunk right after IHDR. PNG stores pixels per meter.
+pub fn set_png_dpi(png: Vec<u8>, dpi: f32) -> Vec<u8> {
+    // Signature (8) + IHDR length, type, 13 data bytes and CRC.
+    let ihdr_end = PNG_SIGNATURE.len() + 4 + 4 + 13 + 4;
+    if !png.starts_with(PNG_SIGNATURE) || png.len() < ihdr_end {
+        return png;
+    }
+
+    let ppm = (dpi * INCHES_PER_METER).round() as u32;
+    let mut body = Vec::with_capacity(13);
+    body.extend_from_slice(b"pHYs");
+    body.extend_from_slice(&ppm.to_be_bytes());
+    body.extend_from_slice(&ppm.to_be_bytes());
+    body.push(1); // unit: meter
+
+    let mut out = Vec::with_capacity(png.len() + 21);
+    out.extend_from_slice(&png[..ihdr_end]);
+    out.extend_from_slice(&9u32.to_be_bytes());
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&crc32fast::hash(&body).to_be_bytes());
+    out.extend_from_slice(&png[ihdr_end..]);
+    out
+}
+
+/// Horizontal resolution stored in the file, if any: PNG pHYs, JPEG JFIF
+/// density, then EXIF XResolution.
+pub fn read_dpi(bytes: &[u8]) -> Option<f32> {
+    png_dpi(bytes).or_else(|| jfif_dpi(bytes)).or_else(|| exif_dpi(bytes))
+}
+
+fn png_dpi(bytes: &[u8]) -> Option<f32> {
+    let mut pos = PNG_SIGNATURE.len();
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into().ok()?) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + len)?;
+        match kind {
+            b"pHYs" if len == 9 && data[8] == 1 => {
+                let ppm = u32::from_be_bytes(data[..4].try_into().ok()?);
+                // Pixels per meter can't hold an exact DPI; round off the noise.
+                return (ppm > 0).then(|| (ppm as f32 / INCHES_PER_METER * 10.0).round() / 10.0);
+            }
+            b"IDAT" | b"IEND" => return None,
+            _ => pos += 12 + len,
+        }
+    }
+    None
+}
+
+fn jfif_dpi(bytes: &[u8]) -> Option<f32> {
+    // SOI, then an APP0 segment: FFE0, length, "JFIF\0", version, unit, Xdensity, Ydensity.
+    if bytes.get(..4)? != [0xFF, 0xD8, 0xFF, 0xE0] || bytes.get(6..11)? != b"JFIF\0" {
+        return None;
+    }
+    let unit = *bytes.get(13)?;
+    let density = u16::from_be_bytes([*bytes.get(14)?, *bytes.get(15)?]) as f32;
+    match unit {
+        1 if density > 0.0 => Some(density),
+        2 if density > 0.0 => Some(density * CM_PER_INCH),
+        _ => None,
+    }
+}
+
+fn exif_dpi(bytes: &[u8]) -> Option<f32> {
+    let exif = kamadak_exif::Reader::new().read_from_container(&mut Cursor::new(bytes)).ok()?;
+    let res = match &exif.get_field(Tag::XResolution, In::PRIMARY)?.value {
+        Value::Rational(v) => v.first()?.to_f32(),
+        _ => return None,
+    };
+    let unit = exif
+        .get_field(Tag::ResolutionUnit, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .unwrap_or(2);
+    match unit {
+        2 if res > 0.0 => Some(res),
+        3 if res > 0.0 => Some(res * CM_PER_INCH),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_png() -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        image::RgbImage::new(3, 2).write_to(&mut buf, image::ImageFormat::Png).unwrap();
+        buf.into_inner()
+    }
+
+    #[test]
+    fn png_dpi_round_trips() {
+        let png = set_png_dpi(small_png(), 300.0);
+        assert_eq!(read_dpi(&png), Some(300.0));
+        // The chunk CRC is valid, so decoders still accept the file.
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (3, 2));
+    }
+
+    #[test]
+    fn png_dpi_leaves_other_data_alone() {
+        assert_eq!(read_dpi(&small_png()), None);
+        let not_png = b"GIF89a not a png".to_vec();
+        assert_eq!(set_png_dpi(not_png.clone(), 300.0), not_png);
+        let truncated = small_png()[..20].to_vec();
+        assert_eq!(set_png_dpi(truncated.clone(), 300.0), truncated);
+    }
+}
@@ -4,6 +4,9 @@ use serde::Serialize;
 use std::io::Cursor;
 use crate::error::{PixoraError, Result};
 
+use super::jxl::encode_jxl;
+use super::resize::{data_url_bytes, decode_bytes};
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExifField {
@@ -25,30 +28,18 @@ pub struct ExifResult {
     pub removed: bool,
 }
 
-fn decode(data_url: &str) -> Result<(DynamicImage, String, Vec<u8>)> {
-    let (header, data) = data_url
-        .split_once(',')
-        .ok_or_else(|| PixoraError::Process("URL inválida".to_string()))?;
-    let format = if header.contains("jpeg") || header.contains("jpg") {
-        "jpeg"
-    } else if header.contains("png") {
-        "png"
-    } else if header.contains("webp") {
-        "webp"
-    } else {
-        "jpeg"
-    };
-    let bytes = general_purpose::STANDARD
-        .decode(data)
-        .map_err(|e| PixoraError::Process(e.to_string()))?;
-    let img = image::load_from_memory(&bytes).map_err(|e| PixoraError::Image(e.to_string()))?;
-    Ok((img, format.to_string(), bytes))
+fn decode(data_url: &str) -> Result<(DynamicImage, String)> {
+    let (bytes, format) = data_url_bytes(data_url)?;
+    let img = decode_bytes(&bytes, &format)?;
+    Ok((img, format))
 }
 
 #[tauri::command]
 pub async fn read_exif(data_url: String) -> Result<ExifInfo> {
     tauri::async_runtime::spawn_blocking(move || {
-        let (_, _, bytes) = decode(&data_url)?;
+        // Only the container is needed here; skipping the pixel decode keeps
+        // RAW files from being developed just to list their tags.
+        let (bytes, _) = data_url_bytes(&data_url)?;
 
         let reader = kamadak_exif::Reader::new();
         match reader.read_from_container(&mut Cursor::new(&bytes)) {
@@ -77,7 +68,7 @@ pub async fn read_exif(data_url: String) -> Result<ExifInfo> {
 #[tauri::command]
 pub async fn strip_exif(data_url: String) -> Result<ExifResult> {
     tauri::async_runtime::spawn_blocking(move || {
-        let (img, format, _) = decode(&data_url)?;
+        let (img, format) = decode(&data_url)?;
 
         let mut buf = Cursor::new(Vec::new());
         match format.as_str() {
@@ -87,6 +78,7 @@ pub async fn strip_exif(data_url: String) -> Result<ExifResult> {
             "webp" => img
                 .write_to(&mut buf, ImageFormat::WebP)
                 .map_err(|e| PixoraError::Image(e.to_string()))?,
+            "jxl" => buf.get_mut().extend(encode_jxl(&img)?),
             _ => {
                 let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, 92);
                 encoder.encode_image(&img).map_err(|e| PixoraError::Image(e.to_string()))?;
@@ -97,6 +89,7 @@ pub async fn strip_exif(data_url: String) -> Result<ExifResult> {
         let mime = match format.as_str() {
             "png" => "image/png",
             "webp" => "image/webp",
+            "jxl" => "image/jxl",
             _ => "image/jpeg",
         };
         let b64 = general_purpose::STANDARD.encode(&bytes);
@@ -0,0 +1,241 @@
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use ndarray::Array;
+use ort::session::Session;
+use ort::value::TensorRef;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::{PixoraError, Result};
+use super::redact::{redact_regions, PixelRegion, RedactEffect, RegionShape};
+use super::remove_bg::{build_session, model_dir};
+use super::resize::decode_data_url;
+
+/// UltraFace-style detector: one `1×3×H×W` input normalized as
+/// `(p - 127) / 128`, and `scores` (`1×N×2`) and `boxes` (`1×N×4`, corner
+/// form, 0–1) outputs.
+const MODEL_FILE: &str = "face_detector.onnx";
+/// Input size of the RFB-320 model, used when the model doesn't fix one.
+const DEFAULT_INPUT: (u32, u32) = (320, 240);
+const MEAN: f32 = 127.0;
+const STD: f32 = 128.0;
+/// Boxes overlapping a stronger one by more than this are dropped.
+const NMS_IOU: f32 = 0.3;
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceBlurSettings {
+    /// Minimum detector confidence, 0–1.
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    /// Extra margin around each face, in percent of its size.
+    #[serde(default = "default_padding")]
+    pub padding: f32,
+    #[serde(default)]
+    pub effect: RedactEffect,
+    #[serde(default = "default_shape")]
+    pub shape: RegionShape,
+}
+
+fn default_threshold() -> f32 {
+    0.7
+}
+
+fn default_padding() -> f32 {
+    15.0
+}
+
+fn default_shape() -> RegionShape {
+    RegionShape::Ellipse
+}
+
+/// A detected face in pixels of the source image.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Face {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub score: f32,
+}
+
+impl Face {
+    fn iou(&self, other: &Face) -> f32 {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= left || bottom <= top {
+            return 0.0;
+        }
+        let inter = ((right - left) * (bottom - top)) as f32;
+        let union = (self.width * self.height + other.width * other.height) as f32 - inter;
+        inter / union
+    }
+}
+
+fn face_model_path() -> Result<PathBuf> {
+    Ok(model_dir()?.join(MODEL_FILE))
+}
+
+fn ensure_session() -> Result<std::sync::MutexGuard<'static, Option<Session>>> {
+    let mut guard = SESSION
+        .lock()
+        .map_err(|e| PixoraError::Lock(e.to_string()))?;
+    if guard.is_none() {
+        let path = face_model_path()?;
+        if !path.exists() {
+            return Err(PixoraError::Process(format!(
+                "Face detection model not found; place an UltraFace ONNX model at {}",
+                path.display()
+            )));
+        }
+        *guard = Some(build_session(&std::fs::read(&path)?)?);
+    }
+    Ok(guard)
+}
+
+/// Model input size as (width, height).
+fn input_size(session: &Session) -> (u32, u32) {
+    let shape = session.inputs().first().and_then(|input| input.dtype().tensor_shape());
+    match shape {
+        Some(shape) if shape.len() == 4 && shape[2] > 0 && shape[3] > 0 => (shape[3] as u32, shape[2] as u32),
+        _ => DEFAULT_INPUT,
+    }
+}
+
+/// Runs the detector on one tile, returning faces offset into image space.
+fn detect_tile(session: &mut Session, tile: &RgbImage, offset: (u32, u32), threshold: f32) -> Result<Vec<Face>> {
+    let (in_w, in_h) = input_size(session);
+    let resized = image::imageops::resize(tile, in_w, in_h, FilterType::Triangle);
+
+    let input_array = Array::from_shape_fn((1, 3, in_h as usize, in_w as usize), |(_n, c, h, w)| {
+        (resized.get_pixel(w as u32, h as u32)[c] as f32 - MEAN) / STD
+    });
+    let input_tensor = TensorRef::from_array_view(&input_array)
+        .map_err(|e: ort::Error| PixoraError::Process(format!("Failed to create input tensor: {}", e)))?;
+
+    let outputs = session
+        .run(ort::inputs![&*input_tensor])
+        .map_err(|e: ort::Error| PixoraError::Process(format!("Inference failed: {}", e)))?;
+    let extract = |name: &str, index: usize, width: usize| {
+        let output = outputs
+            .get(name)
+            .or_else(|| outputs.keys().nth(index).and_then(|key| outputs.get(key)))
+            .ok_or_else(|| PixoraError::Process(format!("Face model has no `{}` output", name)))?;
+        let array = output
+            .try_extract_array::<f32>()
+            .map_err(|e: ort::Error| PixoraError::Process(format!("Failed to extract output: {}", e)))?;
+        match array.shape() {
+            [1, _, w] if *w == width => Ok(array),
+            shape => Err(PixoraError::Process(format!(
+                "Unsupported face model: `{}` output is {:?}, expected [1, N, {}]",
+                name, shape, width
+            ))),
+        }
+    };
+    let scores = extract("scores", 0, 2)?;
+    let boxes = extract("boxes", 1, 4)?;
+    if boxes.shape()[1] != scores.shape()[1] {
+        return Err(PixoraError::Process("Face model returned mismatched scores and boxes".to_string()));
+    }
+
+    let (tw, th) = (tile.width() as f32, tile.height() as f32);
+    let mut faces = Vec::new();
+    for i in 0..scores.shape()[1] {
+        let score = scores[[0, i, 1]];
+        if score < threshold {
+            continue;
+        }
+        let x1 = (boxes[[0, i, 0]].clamp(0.0, 1.0) * tw).round() as u32;
+        let y1 = (boxes[[0, i, 1]].clamp(0.0, 1.0) * th).round() as u32;
+        let x2 = (boxes[[0, i, 2]].clamp(0.0, 1.0) * tw).round() as u32;
+        let y2 = (boxes[[0, i, 3]].clamp(0.0, 1.0) * th).round() as u32;
+        if x2 > x1 && y2 > y1 {
+            faces.push(Face { x: offset.0 + x1, y: offset.1 + y1, width: x2 - x1, height: y2 - y1, score });
+        }
+    }
+    Ok(faces)
+}
+
+/// Detects faces in `img`. Large images are also scanned in overlapping
+/// tiles so small faces in a crowd survive the downscale to the model input.
+pub fn detect_faces(img: &DynamicImage, threshold: f32) -> Result<Vec<Face>> {
+    let mut guard = ensure_session()?;
+    let session = guard.as_mut().unwrap();
+    let (in_w, in_h) = input_size(session);
+
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let threshold = threshold.clamp(0.0, 1.0);
+    let mut faces = detect_tile(session, &rgb, (0, 0), threshold)?;
+
+    if width > in_w * 2 || height > in_h * 2 {
+        // 3×3 grid of half-size tiles, each overlapping its neighbours by half.
+        let (tile_w, tile_h) = (width / 2, height / 2);
+        for row in 0..3 {
+            for col in 0..3 {
+                let (x, y) = (col * tile_w / 2, row * tile_h / 2);
+                let tile = image::imageops::crop_imm(&rgb, x, y, tile_w, tile_h).to_image();
+                faces.extend(detect_tile(session, &tile, (x, y), threshold)?);
+            }
+        }
+    }
+
+    faces.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Face> = Vec::new();
+    for face in faces {
+        if kept.iter().all(|k| k.iou(&face) <= NMS_IOU) {
+            kept.push(face);
+        }
+    }
+    Ok(kept)
+}
+
+/// Blurs or pixelates every detected face.
+pub fn apply_face_blur(img: DynamicImage, settings: &FaceBlurSettings) -> Result<DynamicImage> {
+    let faces = detect_faces(&img, settings.threshold)?;
+    if faces.is_empty() {
+        return Ok(img);
+    }
+
+    let (width, height) = (img.width(), img.height());
+    let pad = settings.padding.max(0.0) / 100.0;
+    let regions: Vec<PixelRegion> = faces
+        .iter()
+        .map(|f| {
+            let (px, py) = ((f.width as f32 * pad).round() as u32, (f.height as f32 * pad).round() as u32);
+            let x = f.x.saturating_sub(px);
+            let y = f.y.saturating_sub(py);
+            let right = (f.x + f.width + px).min(width);
+            let bottom = (f.y + f.height + py).min(height);
+            PixelRegion { x, y, width: right - x, height: bottom - y, shape: settings.shape }
+        })
+        .collect();
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    redact_regions(&mut rgba, &regions, &settings.effect)?;
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
+
+#[tauri::command]
+pub async fn detect_faces_in_image(data_url: String, threshold: Option<f32>) -> Result<Vec<Face>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (img, _format) = decode_data_url(&data_url)?;
+        detect_faces(&img, threshold.unwrap_or_else(default_threshold))
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn check_face_model_exists() -> Result<bool> {
+    let path = face_model_path()?;
+    Ok(path.exists())
+}
@@ -0,0 +1,220 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::color::parse_hex_color;
+use crate::error::Result;
+use super::adjust::{linear_to_srgb, srgb_to_linear};
+
+/// Entries in the linear-to-sRGB table.
+const ENCODE_STEPS: usize = 4096;
+
+/// One look; a pipeline applies a list of them in order.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Filter {
+    /// Rec. 709 luminance computed in linear light.
+    Grayscale,
+    Sepia {
+        /// 0–100.
+        #[serde(default = "default_full")]
+        intensity: f32,
+    },
+    /// Maps luminance onto a gradient between two hex colors.
+    Duotone { shadows: String, highlights: String },
+    Invert,
+    Posterize {
+        /// Tones per channel, 2–255.
+        #[serde(default = "default_levels")]
+        levels: u8,
+    },
+    /// Darkens towards the corners.
+    Vignette {
+        /// 0–100; 100 turns the corners black.
+        #[serde(default = "default_vignette")]
+        amount: f32,
+        /// Where darkening starts, in percent of the center-to-corner distance.
+        #[serde(default = "default_vignette_radius")]
+        radius: f32,
+        /// Width of the falloff, in the same unit as `radius`.
+        #[serde(default = "default_vignette_softness")]
+        softness: f32,
+    },
+    /// Monochrome film grain, strongest in the midtones.
+    Grain {
+        /// 0–100.
+        #[serde(default = "default_grain")]
+        amount: f32,
+        /// Same seed, same grain, so batches and previews match.
+        #[serde(default)]
+        seed: u32,
+    },
+}
+
+fn default_full() -> f32 {
+    100.0
+}
+
+fn default_levels() -> u8 {
+    4
+}
+
+fn default_vignette() -> f32 {
+    50.0
+}
+
+fn default_vignette_radius() -> f32 {
+    50.0
+}
+
+fn default_vignette_softness() -> f32 {
+    50.0
+}
+
+fn default_grain() -> f32 {
+    20.0
+}
+
+/// Shared sRGB conversion tables.
+struct Gamma {
+    decode: [f32; 256],
+    encode: Vec<u8>,
+}
+
+impl Gamma {
+    fn new() -> Self {
+        Gamma {
+            decode: std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)),
+            encode: (0..ENCODE_STEPS)
+                .map(|i| (linear_to_srgb(i as f32 / (ENCODE_STEPS - 1) as f32) * 255.0).round() as u8)
+                .collect(),
+        }
+    }
+
+    /// Linear luminance of an sRGB pixel.
+    fn luminance(&self, px: &Rgba<u8>) -> f32 {
+        0.2126 * self.decode[px[0] as usize] + 0.7152 * self.decode[px[1] as usize] + 0.0722 * self.decode[px[2] as usize]
+    }
+
+    fn encode(&self, linear: f32) -> u8 {
+        self.encode[(linear.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round() as usize]
+    }
+}
+
+/// Cheap per-pixel hash mapped to roughly normal noise with unit spread.
+fn noise(x: u32, y: u32, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    for k in 0..3u32 {
+        let mut h = x.wrapping_mul(0x9E37_79B1) ^ y.wrapping_mul(0x85EB_CA77) ^ seed.wrapping_mul(0xC2B2_AE3D) ^ k.wrapping_mul(0x27D4_EB2F);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x2C1B_3C6D);
+        h ^= h >> 12;
+        h = h.wrapping_mul(0x297A_2D39);
+        h ^= h >> 15;
+        sum += h as f32 / u32::MAX as f32;
+    }
+    (sum - 1.5) * 2.0
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0).max(f32::EPSILON)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn apply_filter(rgba: &mut RgbaImage, filter: &Filter, gamma: &Gamma) -> Result<()> {
+    let (width, height) = rgba.dimensions();
+    match filter {
+        Filter::Grayscale => {
+            for px in rgba.pixels_mut() {
+                let v = gamma.encode(gamma.luminance(px));
+                *px = Rgba([v, v, v, px[3]]);
+            }
+        }
+        Filter::Sepia { intensity } => {
+            let t = intensity.clamp(0.0, 100.0) / 100.0;
+            for px in rgba.pixels_mut() {
+                let [r, g, b] = [px[0] as f32, px[1] as f32, px[2] as f32];
+                let sepia = [
+                    0.393 * r + 0.769 * g + 0.189 * b,
+                    0.349 * r + 0.686 * g + 0.168 * b,
+                    0.272 * r + 0.534 * g + 0.131 * b,
+                ];
+                for c in 0..3 {
+                    px[c] = (px[c] as f32 + (sepia[c] - px[c] as f32) * t).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        Filter::Duotone { shadows, highlights } => {
+            let dark = parse_hex_color(shadows)?;
+            let light = parse_hex_color(highlights)?;
+            for px in rgba.pixels_mut() {
+                let t = gamma.encode(gamma.luminance(px)) as f32 / 255.0;
+                for c in 0..3 {
+                    px[c] = (dark[c] as f32 + (light[c] as f32 - dark[c] as f32) * t).round() as u8;
+                }
+            }
+        }
+        Filter::Invert => {
+            for px in rgba.pixels_mut() {
+                for c in 0..3 {
+                    px[c] = 255 - px[c];
+                }
+            }
+        }
+        Filter::Posterize { levels } => {
+            let steps = (*levels).max(2) as f32 - 1.0;
+            let table: [u8; 256] = std::array::from_fn(|v| ((v as f32 / 255.0 * steps).round() / steps * 255.0).round() as u8);
+            for px in rgba.pixels_mut() {
+                for c in 0..3 {
+                    px[c] = table[px[c] as usize];
+                }
+            }
+        }
+        Filter::Vignette { amount, radius, softness } => {
+            let amount = amount.clamp(0.0, 100.0) / 100.0;
+            let start = radius.max(0.0) / 100.0;
+            let end = start + softness.max(0.0) / 100.0;
+            let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+            // Elliptical, so the corners of any aspect ratio sit at distance 1.
+            for (x, y, px) in rgba.enumerate_pixels_mut() {
+                let dx = (x as f32 + 0.5 - cx) / cx;
+                let dy = (y as f32 + 0.5 - cy) / cy;
+                let d = (dx * dx + dy * dy).sqrt() / std::f32::consts::SQRT_2;
+                let factor = 1.0 - amount * smoothstep(start, end, d);
+                if factor < 1.0 {
+                    for c in 0..3 {
+                        px[c] = gamma.encode(gamma.decode[px[c] as usize] * factor);
+                    }
+                }
+            }
+        }
+        Filter::Grain { amount, seed } => {
+            let strength = amount.clamp(0.0, 100.0) / 100.0 * 40.0;
+            for (x, y, px) in rgba.enumerate_pixels_mut() {
+                let l = (px[0] as f32 * 0.2126 + px[1] as f32 * 0.7152 + px[2] as f32 * 0.0722) / 255.0;
+                let delta = noise(x, y, *seed) * strength * (4.0 * l * (1.0 - l)).max(0.25);
+                for c in 0..3 {
+                    px[c] = (px[c] as f32 + delta).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies `filters` in order, keeping alpha untouched.
+pub fn apply_filters(img: DynamicImage, filters: &[Filter]) -> Result<DynamicImage> {
+    if filters.is_empty() {
+        return Ok(img);
+    }
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    let gamma = Gamma::new();
+    for filter in filters {
+        apply_filter(&mut rgba, filter, &gamma)?;
+    }
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
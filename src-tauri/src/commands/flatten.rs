@@ -0,0 +1,111 @@
+use image::{imageops, imageops::FilterType, DynamicImage, Rgb, RgbImage, Rgba, RgbaImage};
+use imageproc::filter::gaussian_blur_f32;
+use serde::Deserialize;
+
+use crate::color::parse_hex_color;
+use crate::error::Result;
+use super::trim::{trim_rect, TrimMode, TrimSettings};
+
+/// Factor the backdrop is shrunk by before blurring; a large blur hides the
+/// lost detail and runs far faster on the small copy.
+const BLUR_SHRINK: u32 = 8;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum FlattenBackground {
+    /// Hex color, white when unset.
+    Color {
+        #[serde(default)]
+        color: Option<String>,
+    },
+    /// A blurred copy of the visible content, scaled to cover the frame.
+    Blur {
+        /// Blur sigma in percent of the longer side.
+        #[serde(default = "default_blur")]
+        radius: f32,
+    },
+}
+
+impl Default for FlattenBackground {
+    fn default() -> Self {
+        FlattenBackground::Color { color: None }
+    }
+}
+
+fn default_blur() -> f32 {
+    4.0
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FlattenSettings {
+    #[serde(default)]
+    pub background: FlattenBackground,
+}
+
+/// Average color of `rgba`, weighted by alpha; white when fully transparent.
+fn mean_color(rgba: &RgbaImage) -> Rgb<u8> {
+    let mut sum = [0f64; 3];
+    let mut weight = 0f64;
+    for px in rgba.pixels() {
+        let a = px[3] as f64;
+        for c in 0..3 {
+            sum[c] += px[c] as f64 * a;
+        }
+        weight += a;
+    }
+    if weight == 0.0 {
+        return Rgb([255, 255, 255]);
+    }
+    Rgb(sum.map(|s| (s / weight).round() as u8))
+}
+
+fn composite(rgba: &RgbaImage, backdrop: &RgbImage) -> RgbImage {
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let src = rgba.get_pixel(x, y);
+        let dst = backdrop.get_pixel(x, y);
+        let a = src[3] as f32 / 255.0;
+        Rgb(std::array::from_fn(|c| (src[c] as f32 * a + dst[c] as f32 * (1.0 - a)).round() as u8))
+    })
+}
+
+/// Opaque content of `rgba` scaled to cover the whole frame and blurred.
+fn blurred_backdrop(rgba: &RgbaImage, radius: f32) -> Result<RgbImage> {
+    let (width, height) = rgba.dimensions();
+    let content_only = TrimSettings { mode: TrimMode::Transparent, color: None, tolerance: 10, padding: 0 };
+    let rect = trim_rect(rgba, &content_only)?;
+    let content = imageops::crop_imm(rgba, rect.x, rect.y, rect.width, rect.height).to_image();
+    let content = composite(&content, &RgbImage::from_pixel(rect.width, rect.height, mean_color(&content)));
+
+    let small_w = (width / BLUR_SHRINK).max(1);
+    let small_h = (height / BLUR_SHRINK).max(1);
+    let scale = (small_w as f32 / rect.width as f32).max(small_h as f32 / rect.height as f32);
+    let cover_w = ((rect.width as f32 * scale).ceil() as u32).max(small_w);
+    let cover_h = ((rect.height as f32 * scale).ceil() as u32).max(small_h);
+    let cover = imageops::resize(&content, cover_w, cover_h, FilterType::Triangle);
+    let cover = imageops::crop_imm(&cover, (cover_w - small_w) / 2, (cover_h - small_h) / 2, small_w, small_h).to_image();
+
+    let sigma = (width.max(height) as f32 * radius.max(0.0) / 100.0 / BLUR_SHRINK as f32).max(0.5);
+    let blurred = gaussian_blur_f32(&cover, sigma);
+    Ok(imageops::resize(&blurred, width, height, FilterType::Triangle))
+}
+
+/// Composites transparency onto the chosen background so formats without
+/// alpha (JPEG) get a defined result instead of black areas.
+pub fn apply_flatten(img: DynamicImage, settings: &FlattenSettings) -> Result<DynamicImage> {
+    if !img.color().has_alpha() {
+        return Ok(img);
+    }
+    let rgba = img.to_rgba8();
+    let backdrop = match &settings.background {
+        FlattenBackground::Color { color } => {
+            let color = match color.as_deref() {
+                Some(hex) => parse_hex_color(hex)?,
+                None => Rgba([255, 255, 255, 255]),
+            };
+            RgbImage::from_pixel(rgba.width(), rgba.height(), Rgb([color[0], color[1], color[2]]))
+        }
+        FlattenBackground::Blur { radius } => blurred_backdrop(&rgba, *radius)?,
+    };
+    Ok(DynamicImage::ImageRgb8(composite(&rgba, &backdrop)))
+}
@@ -0,0 +1,188 @@
+use image::{DynamicImage, GenericImageView};
+use jxl_oxide::integration::JxlDecoder;
+use jxl_oxide::{JpegReconstructionStatus, JxlImage};
+use serde::Serialize;
+use std::io::{Cursor, ErrorKind};
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_shell::{Error as ShellError, ShellExt};
+use zune_core::bit_depth::BitDepth;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::EncoderOptions;
+use zune_jpegxl::JxlSimpleEncoder;
+
+use crate::error::{PixoraError, Result};
+
+const CODESTREAM_SIG: [u8; 2] = [0xff, 0x0a];
+const CONTAINER_SIG: [u8; 12] = [0, 0, 0, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a];
+
+/// libjxl reference encoder, used for lossless JPEG recompression. It is only
+/// shipped next to the app in builds made with `tauri.cjxl.conf.json`.
+const CJXL: &str = "cjxl";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JxlTranscodeResult {
+    pub output_path: String,
+    pub size_bytes: u64,
+    pub original_size: u64,
+    pub saved_percent: f32,
+}
+
+pub fn is_jxl(bytes: &[u8]) -> bool {
+    bytes.starts_with(&CODESTREAM_SIG) || bytes.starts_with(&CONTAINER_SIG)
+}
+
+pub fn decode_jxl(bytes: &[u8]) -> Result<DynamicImage> {
+    let decoder = JxlDecoder::new(Cursor::new(bytes)).map_err(|e| PixoraError::Image(e.to_string()))?;
+    DynamicImage::from_decoder(decoder).map_err(|e| PixoraError::Image(e.to_string()))
+}
+
+/// Rejects a quality setting for JPEG XL output. The encoder is lossless
+/// only, so the setting would otherwise be silently ignored.
+pub fn check_jxl_quality(format: &str, quality: Option<u8>) -> Result<()> {
+    if format == "jxl" && quality.is_some() {
+        return Err(PixoraError::Process(
+            "JPEG XL output is lossless and has no quality setting; leave quality unset".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Encodes `img` as a lossless JPEG XL codestream. There is no lossy mode,
+/// so commands refuse a quality for it (see `check_jxl_quality`).
+pub fn encode_jxl(img: &DynamicImage) -> Result<Vec<u8>> {
+    let (width, height) = img.dimensions();
+    let (pixels, colorspace) = match img {
+        DynamicImage::ImageLuma8(_) => (img.to_luma8().into_raw(), ColorSpace::Luma),
+        DynamicImage::ImageLumaA8(_) => (img.to_luma_alpha8().into_raw(), ColorSpace::LumaA),
+        _ if img.color().has_alpha() => (img.to_rgba8().into_raw(), ColorSpace::RGBA),
+        _ => (img.to_rgb8().into_raw(), ColorSpace::RGB),
+    };
+
+    let options = EncoderOptions::new(width as usize, height as usize, colorspace, BitDepth::Eight);
+    let mut out = Vec::new();
+    JxlSimpleEncoder::new(&pixels, options)
+        .encode(&mut out)
+        .map_err(|e| PixoraError::Image(format!("JPEG XL encode failed: {}", e)))?;
+    Ok(out)
+}
+
+fn reconstruct_jpeg_bytes(jxl: &[u8]) -> Result<Vec<u8>> {
+    let image = JxlImage::builder()
+        .read(Cursor::new(jxl))
+        .map_err(|e| PixoraError::Image(e.to_string()))?;
+
+    match image.jpeg_reconstruction_status() {
+        JpegReconstructionStatus::Available => {}
+        JpegReconstructionStatus::Invalid => {
+            return Err(PixoraError::Image("JPEG reconstruction data is invalid".to_string()))
+        }
+        _ => {
+            return Err(PixoraError::Process(
+                "This JPEG XL file was not created from a JPEG".to_string(),
+            ))
+        }
+    }
+
+    let mut jpeg = Vec::new();
+    image
+        .reconstruct_jpeg(&mut jpeg)
+        .map_err(|e| PixoraError::Image(format!("JPEG reconstruction failed: {}", e)))?;
+    Ok(jpeg)
+}
+
+fn transcode_result(output_path: PathBuf, original_size: u64) -> Result<JxlTranscodeResult> {
+    let size_bytes = output_path.metadata()?.len();
+    let saved_percent = if original_size > 0 {
+        ((original_size as f32 - size_bytes as f32) / original_size as f32) * 100.0
+    } else {
+        0.0
+    };
+    Ok(JxlTranscodeResult {
+        output_path: output_path.to_string_lossy().into_owned(),
+        size_bytes,
+        original_size,
+        saved_percent,
+    })
+}
+
+/// Losslessly recompresses a JPEG into JPEG XL, keeping the data needed to
+/// rebuild the original file bit for bit. The result is verified by
+/// reconstructing it before it is left on disk.
+#[tauri::command]
+pub async fn transcode_jpeg_to_jxl(app: AppHandle, src: String, dest: String) -> Result<JxlTranscodeResult> {
+    let original = tokio::fs::read(&src).await?;
+    if !original.starts_with(&[0xff, 0xd8]) {
+        return Err(PixoraError::Process(format!("{} is not a JPEG file", src)));
+    }
+
+    let output = app
+        .shell()
+        .sidecar(CJXL)
+        .map_err(|e| PixoraError::Process(format!("Could not find bundled {}: {}", CJXL, e)))?
+        .args([src.as_str(), dest.as_str(), "--lossless_jpeg=1", "--quiet"])
+        .output()
+        .await
+        .map_err(|e| match e {
+            ShellError::Io(e) if e.kind() == ErrorKind::NotFound => PixoraError::Process(format!(
+                "JPEG XL recompression is unavailable: {} is not bundled with this build",
+                CJXL
+            )),
+            e => PixoraError::Process(format!("Could not run {}: {}", CJXL, e)),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(PixoraError::Process(format!(
+            "{} exited with {:?}: {}",
+            CJXL,
+            output.status.code(),
+            stderr.trim()
+        )));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let jxl = std::fs::read(&dest)?;
+        if reconstruct_jpeg_bytes(&jxl)? != original {
+            let _ = std::fs::remove_file(&dest);
+            return Err(PixoraError::Process(
+                "JPEG XL round trip does not match the original JPEG".to_string(),
+            ));
+        }
+
+        transcode_result(PathBuf::from(dest), original.len() as u64)
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+/// Rebuilds the original JPEG from a JPEG XL file produced by `transcode_jpeg_to_jxl`.
+#[tauri::command]
+pub async fn reconstruct_jpeg(src: String, dest: String) -> Result<JxlTranscodeResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let jxl = std::fs::read(&src)?;
+        let jpeg = reconstruct_jpeg_bytes(&jxl)?;
+        std::fs::write(&dest, &jpeg)?;
+        transcode_result(PathBuf::from(dest), jxl.len() as u64)
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_is_only_rejected_for_jxl() {
+        assert!(check_jxl_quality("jxl", Some(80)).is_err());
+        assert!(check_jxl_quality("jxl", None).is_ok());
+        assert!(check_jxl_quality("webp", Some(80)).is_ok());
+    }
+
+    #[test]
+    fn encodes_losslessly() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(5, 3, |x, y| {
+            image::Rgba([x as u8 * 50, y as u8 * 80, 7, 255 - x as u8])
+        }));
+        let bytes = encode_jxl(&img).unwrap();
+        assert!(is_jxl(&bytes));
+        assert_eq!(decode_jxl(&bytes).unwrap().to_rgba8(), img.to_rgba8());
+    }
+}
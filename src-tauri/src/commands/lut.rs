@@ -0,0 +1,384 @@
+use image::DynamicImage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::error::{PixoraError, Result};
+
+/// Grid size used when sampling the built-in looks into a 3D LUT.
+const BUILTIN_SIZE: usize = 33;
+/// `.cube` files larger than this per side are rejected.
+const MAX_3D_SIZE: usize = 256;
+const MAX_1D_SIZE: usize = 65536;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum BuiltinLook {
+    #[serde(alias = "blackAndWhite")]
+    Bw,
+    Warm,
+    Cool,
+    Sepia,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum LutSource {
+    /// A 1D or 3D `.cube` file.
+    File { path: String },
+    Builtin { look: BuiltinLook },
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LutInterpolation {
+    Trilinear,
+    #[default]
+    Tetrahedral,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LutSettings {
+    pub source: LutSource,
+    /// Only affects 3D LUTs.
+    #[serde(default)]
+    pub interpolation: LutInterpolation,
+    /// 0–100; how much of the graded result is mixed over the original.
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+}
+
+fn default_intensity() -> f32 {
+    100.0
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Dimension {
+    One,
+    Three,
+}
+
+/// A parsed LUT. 3D tables are stored red-fastest, as in `.cube` files.
+#[derive(Debug)]
+pub struct CubeLut {
+    dimension: Dimension,
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    table: Vec<[f32; 3]>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    File(PathBuf, Option<SystemTime>),
+    Builtin(BuiltinLook),
+}
+
+/// Parsed LUTs, kept for the whole session so a batch parses each file once.
+/// File entries are keyed by modification time, so edited files are re-read.
+static CACHE: Mutex<Option<HashMap<CacheKey, Arc<CubeLut>>>> = Mutex::new(None);
+
+fn parse_floats<const N: usize>(parts: &[&str], line: usize) -> Result<[f32; N]> {
+    let invalid = || PixoraError::Process(format!("Invalid .cube data on line {}", line));
+    if parts.len() != N {
+        return Err(invalid());
+    }
+    let mut out = [0f32; N];
+    for (v, p) in out.iter_mut().zip(parts) {
+        *v = p.parse().map_err(|_| invalid())?;
+    }
+    Ok(out)
+}
+
+pub fn parse_cube(text: &str) -> Result<CubeLut> {
+    let mut dimension = None;
+    let mut size = 0usize;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut table = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let size_of = |max: usize| -> Result<usize> {
+            let n: usize = parts.get(1).and_then(|p| p.parse().ok()).unwrap_or(0);
+            if n < 2 || n > max {
+                return Err(PixoraError::Process(format!("Unsupported LUT size on line {}", i + 1)));
+            }
+            Ok(n)
+        };
+        match parts[0] {
+            "TITLE" => {}
+            "LUT_1D_SIZE" => {
+                size = size_of(MAX_1D_SIZE)?;
+                dimension = Some(Dimension::One);
+            }
+            "LUT_3D_SIZE" => {
+                size = size_of(MAX_3D_SIZE)?;
+                dimension = Some(Dimension::Three);
+            }
+            "DOMAIN_MIN" => domain_min = parse_floats(&parts[1..], i + 1)?,
+            "DOMAIN_MAX" => domain_max = parse_floats(&parts[1..], i + 1)?,
+            "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                let [lo, hi] = parse_floats(&parts[1..], i + 1)?;
+                domain_min = [lo; 3];
+                domain_max = [hi; 3];
+            }
+            _ if parts[0].chars().next().is_some_and(|c| c.is_ascii_alphabetic()) => {}
+            _ => table.push(parse_floats(&parts, i + 1)?),
+        }
+    }
+
+    let dimension = dimension.ok_or_else(|| PixoraError::Process("Missing LUT_1D_SIZE or LUT_3D_SIZE".to_string()))?;
+    let expected = match dimension {
+        Dimension::One => size,
+        Dimension::Three => size * size * size,
+    };
+    if table.len() != expected {
+        return Err(PixoraError::Process(format!(
+            "LUT has {} entries, expected {}",
+            table.len(),
+            expected
+        )));
+    }
+    if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+        return Err(PixoraError::Process("Invalid LUT domain".to_string()));
+    }
+    Ok(CubeLut { dimension, size, domain_min, domain_max, table })
+}
+
+fn look_color(look: BuiltinLook, [r, g, b]: [f32; 3]) -> [f32; 3] {
+    match look {
+        BuiltinLook::Bw => {
+            let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [y, y, y]
+        }
+        BuiltinLook::Warm => [r * 1.06 + 0.02, g * 1.01, b * 0.88],
+        BuiltinLook::Cool => [r * 0.9, g * 0.99 + 0.01, b * 1.06 + 0.02],
+        BuiltinLook::Sepia => [
+            0.393 * r + 0.769 * g + 0.189 * b,
+            0.349 * r + 0.686 * g + 0.168 * b,
+            0.272 * r + 0.534 * g + 0.131 * b,
+        ],
+    }
+}
+
+fn builtin_lut(look: BuiltinLook) -> CubeLut {
+    let n = BUILTIN_SIZE;
+    let step = 1.0 / (n - 1) as f32;
+    let mut table = Vec::with_capacity(n * n * n);
+    for b in 0..n {
+        for g in 0..n {
+            for r in 0..n {
+                let c = look_color(look, [r as f32 * step, g as f32 * step, b as f32 * step]);
+                table.push(c.map(|v| v.clamp(0.0, 1.0)));
+            }
+        }
+    }
+    CubeLut { dimension: Dimension::Three, size: n, domain_min: [0.0; 3], domain_max: [1.0; 3], table }
+}
+
+/// Returns the LUT for `source`, parsing it only the first time.
+pub fn load_lut(source: &LutSource) -> Result<Arc<CubeLut>> {
+    let key = match source {
+        LutSource::File { path } => {
+            let path = PathBuf::from(path);
+            let modified = std::fs::metadata(&path)?.modified().ok();
+            CacheKey::File(path, modified)
+        }
+        LutSource::Builtin { look } => CacheKey::Builtin(*look),
+    };
+
+    if let Some(lut) = CACHE
+        .lock()
+        .map_err(|e| PixoraError::Lock(e.to_string()))?
+        .as_ref()
+        .and_then(|cache| cache.get(&key))
+    {
+        return Ok(lut.clone());
+    }
+
+    let lut = Arc::new(match &key {
+        CacheKey::File(path, _) => parse_cube(&std::fs::read_to_string(path)?)?,
+        CacheKey::Builtin(look) => builtin_lut(*look),
+    });
+    let mut guard = CACHE.lock().map_err(|e| PixoraError::Lock(e.to_string()))?;
+    let cache = guard.get_or_insert_with(HashMap::new);
+    if let CacheKey::File(path, _) = &key {
+        cache.retain(|k, _| !matches!(k, CacheKey::File(p, _) if p == path));
+    }
+    cache.insert(key, lut.clone());
+    Ok(lut)
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+/// Weighted sum of the four corners of a tetrahedron.
+fn tetra(w: [f32; 4], c: [[f32; 3]; 4]) -> [f32; 3] {
+    let mut out = [0f32; 3];
+    for (k, v) in out.iter_mut().enumerate() {
+        *v = w[0] * c[0][k] + w[1] * c[1][k] + w[2] * c[2][k] + w[3] * c[3][k];
+    }
+    out
+}
+
+impl CubeLut {
+    /// Grid position of `rgb`, split into integer cell and fraction.
+    fn locate(&self, rgb: [f32; 3]) -> ([usize; 3], [f32; 3]) {
+        let max = (self.size - 1) as f32;
+        let mut cell = [0usize; 3];
+        let mut frac = [0f32; 3];
+        for c in 0..3 {
+            let t = ((rgb[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c])).clamp(0.0, 1.0) * max;
+            let i = (t.floor() as usize).min(self.size - 2);
+            cell[c] = i;
+            frac[c] = t - i as f32;
+        }
+        (cell, frac)
+    }
+
+    fn sample_1d(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let (cell, frac) = self.locate(rgb);
+        let mut out = [0f32; 3];
+        for c in 0..3 {
+            let a = self.table[cell[c]][c];
+            let b = self.table[cell[c] + 1][c];
+            out[c] = a + (b - a) * frac[c];
+        }
+        out
+    }
+
+    fn sample_3d(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let n = self.size;
+        let ([r, g, b], [fr, fg, fb]) = self.locate(rgb);
+        let at = |dr: usize, dg: usize, db: usize| self.table[(r + dr) + (g + dg) * n + (b + db) * n * n];
+        let c000 = at(0, 0, 0);
+        let c111 = at(1, 1, 1);
+
+        match interpolation {
+            LutInterpolation::Trilinear => {
+                let c00 = lerp(c000, at(1, 0, 0), fr);
+                let c10 = lerp(at(0, 1, 0), at(1, 1, 0), fr);
+                let c01 = lerp(at(0, 0, 1), at(1, 0, 1), fr);
+                let c11 = lerp(at(0, 1, 1), c111, fr);
+                lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+            }
+            LutInterpolation::Tetrahedral => {
+                if fr > fg {
+                    if fg > fb {
+                        tetra([1.0 - fr, fr - fg, fg - fb, fb], [c000, at(1, 0, 0), at(1, 1, 0), c111])
+                    } else if fr > fb {
+                        tetra([1.0 - fr, fr - fb, fb - fg, fg], [c000, at(1, 0, 0), at(1, 0, 1), c111])
+                    } else {
+                        tetra([1.0 - fb, fb - fr, fr - fg, fg], [c000, at(0, 0, 1), at(1, 0, 1), c111])
+                    }
+                } else if fb > fg {
+                    tetra([1.0 - fb, fb - fg, fg - fr, fr], [c000, at(0, 0, 1), at(0, 1, 1), c111])
+                } else if fb > fr {
+                    tetra([1.0 - fg, fg - fb, fb - fr, fr], [c000, at(0, 1, 0), at(0, 1, 1), c111])
+                } else {
+                    tetra([1.0 - fg, fg - fr, fr - fb, fb], [c000, at(0, 1, 0), at(1, 1, 0), c111])
+                }
+            }
+        }
+    }
+
+    pub fn sample(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        match self.dimension {
+            Dimension::One => self.sample_1d(rgb),
+            Dimension::Three => self.sample_3d(rgb, interpolation),
+        }
+    }
+}
+
+/// Grades the color channels through the LUT; alpha is left untouched.
+pub fn apply_lut(img: DynamicImage, settings: &LutSettings) -> Result<DynamicImage> {
+    let mix = settings.intensity.clamp(0.0, 100.0) / 100.0;
+    if mix == 0.0 {
+        return Ok(img);
+    }
+    let lut = load_lut(&settings.source)?;
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    for px in rgba.pixels_mut() {
+        let orig = [px[0] as f32 / 255.0, px[1] as f32 / 255.0, px[2] as f32 / 255.0];
+        let graded = lut.sample(orig, settings.interpolation);
+        for c in 0..3 {
+            let v = orig[c] + (graded[c] - orig[c]) * mix;
+            px[c] = (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY_3D: &str = "\
+TITLE \"identity\"
+# comment
+LUT_3D_SIZE 2
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 1 1
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1  # trailing comment
+";
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        (0..3).all(|c| (a[c] - b[c]).abs() < 1e-5)
+    }
+
+    #[test]
+    fn parses_3d_cube() {
+        let lut = parse_cube(IDENTITY_3D).unwrap();
+        assert_eq!((lut.dimension, lut.size, lut.table.len()), (Dimension::Three, 2, 8));
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+            let rgb = [0.2, 0.5, 0.9];
+            assert!(close(lut.sample(rgb, interpolation), rgb));
+        }
+    }
+
+    #[test]
+    fn parses_1d_cube_with_input_range() {
+        let lut = parse_cube("LUT_1D_SIZE 2\nLUT_1D_INPUT_RANGE 0 2\n1 1 1\n0 0 0\n").unwrap();
+        assert_eq!((lut.dimension, lut.domain_max), (Dimension::One, [2.0; 3]));
+        assert!(close(lut.sample([1.0, 0.0, 2.0], LutInterpolation::default()), [0.5, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn rejects_malformed_cubes() {
+        let cases = [
+            "0 0 0\n1 1 1\n",
+            "LUT_3D_SIZE 2\n0 0 0\n",
+            "LUT_3D_SIZE 1\n0 0 0\n",
+            "LUT_3D_SIZE 999\n",
+            "LUT_1D_SIZE 2\n0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\n0 0 x\n1 1 1\n",
+            "LUT_1D_SIZE 2\nDOMAIN_MIN 1 1 1\nDOMAIN_MAX 0 0 0\n0 0 0\n1 1 1\n",
+        ];
+        for text in cases {
+            assert!(parse_cube(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -1,7 +1,31 @@
+pub mod adjust;
+pub mod animation;
+pub mod canvas;
 pub mod compress;
+pub mod denoise;
+pub mod deskew;
+pub mod dpi;
 pub mod exif;
+pub mod faces;
+pub mod filters;
+pub mod flatten;
+pub mod jxl;
+pub mod lut;
+pub mod perspective;
 pub mod pipeline;
+pub mod quantize;
+pub mod raw;
+pub mod redact;
 pub mod remove_bg;
+pub mod resample;
 pub mod resize;
 pub mod save;
+pub mod sharpen;
+pub mod smart_crop;
+pub mod svg;
 pub mod system;
+pub mod text;
+pub mod text_layer;
+pub mod transform;
+pub mod trim;
+pub mod watermark;
@@ -0,0 +1,325 @@
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView, Luma, Rgba, RgbaImage};
+use imageproc::contours::{find_contours, BorderType};
+use imageproc::contrast::adaptive_threshold;
+use imageproc::distance_transform::Norm;
+use imageproc::edges::canny;
+use imageproc::geometric_transformations::{warp_into, Border, Interpolation, Projection};
+use imageproc::geometry::{contour_area, convex_hull};
+use imageproc::morphology::dilate;
+use imageproc::point::Point;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PixoraError, Result};
+use super::jxl::check_jxl_quality;
+use super::resize::{decode_data_url, encode_image};
+
+/// Longest side of the copy the outline is searched on.
+const WORK_PX: u32 = 512;
+const CANNY_LOW: f32 = 30.0;
+const CANNY_HIGH: f32 = 90.0;
+/// Smallest outline worth straightening, as a share of the image area.
+const MIN_AREA_SHARE: f64 = 0.2;
+/// Share of its convex hull a four-corner outline must keep to count as a quad.
+const MIN_QUAD_FILL: f64 = 0.9;
+/// Hull vertices kept before searching for the best four.
+const HULL_VERTICES: usize = 24;
+/// Largest output side we are willing to allocate.
+const MAX_SIDE: u32 = 16384;
+
+/// A point as fractions (0–1) of the image size.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Corner {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum PerspectiveCorners {
+    /// Four corners in any order.
+    Manual { corners: [Corner; 4] },
+    /// The largest four-sided outline in the image, such as a page edge.
+    Auto,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSettings {
+    /// Neighbourhood each pixel is compared against, in percent of the
+    /// shorter side.
+    #[serde(default = "default_scan_radius")]
+    pub radius: f32,
+    /// How much darker than its surroundings a pixel must be to turn black.
+    #[serde(default = "default_scan_offset")]
+    pub offset: i32,
+}
+
+fn default_scan_radius() -> f32 {
+    2.0
+}
+
+fn default_scan_offset() -> i32 {
+    10
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PerspectiveSettings {
+    pub corners: PerspectiveCorners,
+    /// Output size in pixels, at most 16384 per side. When one or both are
+    /// unset they follow the edge lengths of the quad.
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Black-and-white adaptive threshold, like a flatbed scan.
+    #[serde(default)]
+    pub scan: Option<ScanSettings>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerspectiveResult {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+    /// Corners that were used, ordered top-left, top-right, bottom-right,
+    /// bottom-left.
+    pub corners: [Corner; 4],
+}
+
+/// Orders four points top-left, top-right, bottom-right, bottom-left by
+/// their angle around the centroid, so any convex quad keeps all four.
+fn order_corners(points: [(f32, f32); 4]) -> [(f32, f32); 4] {
+    let cx = points.iter().map(|p| p.0).sum::<f32>() / 4.0;
+    let cy = points.iter().map(|p| p.1).sum::<f32>() / 4.0;
+    let mut sorted = points;
+    // With y pointing down, increasing angle runs clockwise on screen.
+    sorted.sort_by(|a, b| (a.1 - cy).atan2(a.0 - cx).total_cmp(&(b.1 - cy).atan2(b.0 - cx)));
+    let first = (0..4)
+        .min_by(|&i, &j| (sorted[i].0 + sorted[i].1).total_cmp(&(sorted[j].0 + sorted[j].1)))
+        .unwrap_or(0);
+    sorted.rotate_left(first);
+    sorted
+}
+
+fn triangle_area2(a: Point<i32>, b: Point<i32>, c: Point<i32>) -> i64 {
+    ((b.x - a.x) as i64 * (c.y - a.y) as i64 - (b.y - a.y) as i64 * (c.x - a.x) as i64).abs()
+}
+
+/// Largest quadrilateral with corners on a convex polygon. Vertices that
+/// barely bend the outline are dropped first so the exhaustive search
+/// stays small.
+fn reduce_to_quad(mut hull: Vec<Point<i32>>) -> Option<[Point<i32>; 4]> {
+    while hull.len() > HULL_VERTICES {
+        let n = hull.len();
+        let drop = (0..n).min_by_key(|&i| triangle_area2(hull[(i + n - 1) % n], hull[i], hull[(i + 1) % n]))?;
+        hull.remove(drop);
+    }
+    let n = hull.len();
+    let mut best: Option<([usize; 4], i64)> = None;
+    for i in 0..n {
+        for j in i + 1..n {
+            for k in j + 1..n {
+                for l in k + 1..n {
+                    let area = triangle_area2(hull[i], hull[j], hull[k]) + triangle_area2(hull[i], hull[k], hull[l]);
+                    if best.is_none_or(|(_, a)| area > a) {
+                        best = Some(([i, j, k, l], area));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(idx, _)| idx.map(|i| hull[i]))
+}
+
+/// Finds the largest four-sided outline, returning its corners in pixels.
+pub fn detect_quad(img: &DynamicImage) -> Result<[(f32, f32); 4]> {
+    let (width, height) = img.dimensions();
+    let scale = (WORK_PX as f32 / width.max(height) as f32).min(1.0);
+    let gray = img.to_luma8();
+    let gray = if scale < 1.0 {
+        let w = ((width as f32 * scale).round() as u32).max(1);
+        let h = ((height as f32 * scale).round() as u32).max(1);
+        imageops::resize(&gray, w, h, FilterType::Triangle)
+    } else {
+        gray
+    };
+
+    // Thicken the edges so small gaps along a page border don't split it.
+    let edges = dilate(&canny(&gray, CANNY_LOW, CANNY_HIGH), Norm::LInf, 1);
+    let min_area = (gray.width() * gray.height()) as f64 * MIN_AREA_SHARE;
+
+    let mut best: Option<([Point<i32>; 4], f64)> = None;
+    for contour in find_contours::<i32>(&edges) {
+        if contour.border_type != BorderType::Outer || contour.points.len() < 4 {
+            continue;
+        }
+        let hull = convex_hull(contour.points);
+        let hull_area = contour_area(&hull);
+        if hull_area < min_area {
+            continue;
+        }
+        let Some(quad) = reduce_to_quad(hull) else { continue };
+        let area = contour_area(&quad);
+        if area >= hull_area * MIN_QUAD_FILL && best.as_ref().is_none_or(|(_, a)| area > *a) {
+            best = Some((quad, area));
+        }
+    }
+
+    let (quad, _) = best.ok_or_else(|| {
+        PixoraError::Process("No document outline found; set the corners manually".to_string())
+    })?;
+    let (sx, sy) = (width as f32 / gray.width() as f32, height as f32 / gray.height() as f32);
+    Ok(order_corners(quad.map(|p| ((p.x as f32 + 0.5) * sx, (p.y as f32 + 0.5) * sy))))
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Output size for the ordered quad `c`, honouring any requested side.
+fn target_size(c: &[(f32, f32); 4], width: Option<u32>, height: Option<u32>) -> Result<(u32, u32)> {
+    let natural_w = distance(c[0], c[1]).max(distance(c[3], c[2])).max(1.0);
+    let natural_h = distance(c[0], c[3]).max(distance(c[1], c[2])).max(1.0);
+    let (w, h) = match (width, height) {
+        (Some(w), Some(h)) => (w as f32, h as f32),
+        (Some(w), None) => (w as f32, w as f32 * natural_h / natural_w),
+        (None, Some(h)) => (h as f32 * natural_w / natural_h, h as f32),
+        (None, None) => (natural_w, natural_h),
+    };
+    if w.round() > MAX_SIDE as f32 || h.round() > MAX_SIDE as f32 {
+        return Err(PixoraError::Process(format!(
+            "Perspective output size {}x{} exceeds the {} px limit",
+            w.round(),
+            h.round(),
+            MAX_SIDE
+        )));
+    }
+    Ok(((w.round() as u32).max(1), (h.round() as u32).max(1)))
+}
+
+fn apply_scan(rgba: &RgbaImage, scan: &ScanSettings) -> RgbaImage {
+    let gray = DynamicImage::ImageRgba8(rgba.clone()).to_luma8();
+    let radius = (gray.width().min(gray.height()) as f32 * scan.radius.max(0.0) / 100.0).round() as u32;
+    let binary = adaptive_threshold(&gray, radius.max(1), scan.offset);
+    RgbaImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let Luma([v]) = *binary.get_pixel(x, y);
+        Rgba([v, v, v, rgba.get_pixel(x, y)[3]])
+    })
+}
+
+/// Corners in pixels for `settings`, detecting them for `auto`.
+pub fn perspective_corners(img: &DynamicImage, settings: &PerspectiveSettings) -> Result<[(f32, f32); 4]> {
+    let (width, height) = img.dimensions();
+    match &settings.corners {
+        PerspectiveCorners::Manual { corners } => Ok(order_corners(corners.map(|c| {
+            (c.x.clamp(0.0, 1.0) * width as f32, c.y.clamp(0.0, 1.0) * height as f32)
+        }))),
+        PerspectiveCorners::Auto => detect_quad(img),
+    }
+}
+
+/// Warps the quad with the given `corners` (ordered, in pixels) onto a flat
+/// rectangle.
+pub fn apply_perspective_corners(
+    img: DynamicImage,
+    corners: [(f32, f32); 4],
+    settings: &PerspectiveSettings,
+) -> Result<DynamicImage> {
+    let (out_w, out_h) = target_size(&corners, settings.width, settings.height)?;
+    let (w, h) = (out_w as f32, out_h as f32);
+    let projection = Projection::from_control_points(corners, [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)])
+        .ok_or_else(|| PixoraError::Process("Corners don't form a quadrilateral".to_string()))?;
+
+    let has_alpha = img.color().has_alpha();
+    let rgba = img.to_rgba8();
+    let mut out = RgbaImage::new(out_w, out_h);
+    warp_into(&rgba, projection, Interpolation::Bilinear, Border::Replicate, &mut out);
+    if let Some(scan) = &settings.scan {
+        out = apply_scan(&out, scan);
+    }
+
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(out)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(out).to_rgb8())
+    })
+}
+
+/// Warps the quad onto a flat rectangle, returning it with the corners used
+/// in pixels.
+pub fn apply_perspective(img: DynamicImage, settings: &PerspectiveSettings) -> Result<(DynamicImage, [(f32, f32); 4])> {
+    let corners = perspective_corners(&img, settings)?;
+    Ok((apply_perspective_corners(img, corners, settings)?, corners))
+}
+
+#[tauri::command]
+pub async fn perspective_image(
+    data_url: String,
+    perspective: PerspectiveSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<PerspectiveResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (img, orig_format) = decode_data_url(&data_url)?;
+        let format = format.unwrap_or(orig_format);
+        check_jxl_quality(&format, quality)?;
+        let quality = quality.unwrap_or(85).clamp(1, 100);
+
+        let (width, height) = img.dimensions();
+        let (out, corners) = apply_perspective(img, &perspective)?;
+        let (data_url, size_bytes) = encode_image(&out, &format, quality)?;
+        Ok(PerspectiveResult {
+            data_url,
+            width: out.width(),
+            height: out.height(),
+            size_bytes,
+            corners: corners.map(|(x, y)| Corner { x: x / width as f32, y: y / height as f32 }),
+        })
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_corners_from_any_order() {
+        let ordered = [(10.0, 5.0), (90.0, 0.0), (100.0, 80.0), (0.0, 70.0)];
+        for shift in 0..4 {
+            let mut points = ordered;
+            points.rotate_left(shift);
+            points.swap(1, 3);
+            assert_eq!(order_corners(points), ordered);
+        }
+    }
+
+    #[test]
+    fn orders_corners_of_a_tilted_quad() {
+        // A square turned by about 40°, where the top-left corner sits near
+        // the middle of the top edge.
+        let points = [(50.0, 0.0), (100.0, 45.0), (55.0, 100.0), (0.0, 55.0)];
+        assert_eq!(order_corners([points[2], points[0], points[3], points[1]]), points);
+    }
+
+    #[test]
+    fn target_size_follows_the_quad() {
+        let quad = [(0.0, 0.0), (200.0, 0.0), (200.0, 100.0), (0.0, 100.0)];
+        assert_eq!(target_size(&quad, None, None).unwrap(), (200, 100));
+        assert_eq!(target_size(&quad, Some(400), None).unwrap(), (400, 200));
+        assert_eq!(target_size(&quad, None, Some(50)).unwrap(), (100, 50));
+        assert_eq!(target_size(&quad, Some(30), Some(30)).unwrap(), (30, 30));
+    }
+
+    #[test]
+    fn target_size_rejects_huge_output() {
+        let quad = [(0.0, 0.0), (200.0, 0.0), (200.0, 100.0), (0.0, 100.0)];
+        assert!(target_size(&quad, Some(MAX_SIDE + 1), Some(10)).is_err());
+        // Keeping the aspect ratio pushes the width past the limit.
+        assert!(target_size(&quad, None, Some(10_000)).is_err());
+        assert!(target_size(&quad, Some(MAX_SIDE), None).is_ok());
+    }
+}
@@ -1,7 +1,6 @@
 use base64::{engine::general_purpose, Engine as _};
-use image::{imageops::FilterType, GenericImageView, ImageFormat};
+use image::{DynamicImage, Frame, GenericImageView};
 use serde::{Deserialize, Serialize};
-use std::io::BufWriter;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tauri::{AppHandle, Manager, State};
@@ -9,8 +8,30 @@ use tauri::{AppHandle, Manager, State};
 use crate::error::{PixoraError, Result};
 use crate::state::PixoraState;
 
+use super::adjust::{apply_adjustments, measure_auto_correction, AdjustSettings, AutoCorrection};
+use super::animation::{can_animate, decode_frames, encode_animation, encode_indexed_gif, map_frames, pick_frame};
+use super::canvas::{apply_canvas, CanvasSettings};
+use super::denoise::{apply_denoise, DenoiseSettings};
+use super::deskew::{apply_deskew_angle, detect_skew, DeskewSettings};
+use super::faces::{apply_face_blur, FaceBlurSettings};
+use super::filters::{apply_filters, Filter};
+use super::flatten::{apply_flatten, FlattenSettings};
+use super::jxl::check_jxl_quality;
+use super::lut::{apply_lut, LutSettings};
+use super::perspective::{apply_perspective_corners, perspective_corners, PerspectiveSettings};
+use super::quantize::{build_palette, encode_indexed, has_transparency, quantize, quantize_with, QuantizeSettings};
+use super::redact::{apply_redact, RedactSettings};
 use super::remove_bg::apply_remove_bg;
-use super::resize::decode_data_url;
+use super::resample::ResampleFilter;
+use super::resize::{
+    data_url_bytes, decode_bytes, encode_bytes, parse_background, resize_to_box, BoxResize, ResizeMode, ResizeSizing,
+};
+use super::sharpen::{apply_sharpen, SharpenSettings};
+use super::smart_crop::{apply_smart_crop, find_smart_crop, SmartCrop, SmartCropSettings};
+use super::text_layer::{apply_text_layers, TextLayer};
+use super::transform::{apply_crop, apply_flip, apply_rotate, Anchor, CropSettings, FlipSettings, RotateSettings};
+use super::trim::{apply_trim_rect, frames_trim_rect, trim_rect, TrimRect, TrimSettings};
+use super::watermark::{apply_watermark, MarkCache, WatermarkSettings};
 
 static COUNTER: AtomicU64 = AtomicU64::new(0);
 
@@ -22,7 +43,7 @@ fn pixora_temp_dir(app: &AppHandle) -> Result<PathBuf> {
     Ok(dir)
 }
 
-fn next_temp_path(app: &AppHandle, ext: &str) -> Result<PathBuf> {
+pub fn next_temp_path(app: &AppHandle, ext: &str) -> Result<PathBuf> {
     let n = COUNTER.fetch_add(1, Ordering::Relaxed);
     let pid = std::process::id();
     Ok(pixora_temp_dir(app)?.join(format!("{pid}-{n}.{ext}")))
@@ -51,11 +72,98 @@ pub async fn cleanup_all(app: &AppHandle, state: &State<'_, PixoraState>) -> Res
 #[serde(rename_all = "camelCase")]
 pub struct ProcessSettings {
     pub format: String,
-    pub quality: u8,
+    /// 85 when unset. Must be unset for JPEG XL, which is always lossless.
+    #[serde(default)]
+    pub quality: Option<u8>,
     pub resize_enabled: bool,
     pub resize_max_px: u32,
     pub resize_custom_h: u32,
+    /// How the image is put into the `resize_max_px` × `resize_custom_h` box.
+    #[serde(default)]
+    pub resize_mode: ResizeMode,
+    #[serde(default)]
+    pub resize_allow_upscale: bool,
+    /// Crop position for `cover`, placement for `contain`.
+    #[serde(default)]
+    pub resize_anchor: Anchor,
+    /// Hex padding color for `contain`; transparent when unset.
+    #[serde(default)]
+    pub resize_background: Option<String>,
+    /// Percentage, megapixel or physical sizing; replaces the pixel box.
+    #[serde(default)]
+    pub resize_sizing: Option<ResizeSizing>,
+    #[serde(default)]
+    pub resize_filter: ResampleFilter,
+    /// Tonal and color corrections, applied after resizing. Auto levels and
+    /// white balance for an animation are measured on its first frame.
+    #[serde(default)]
+    pub adjust: Option<AdjustSettings>,
+    /// Color grading through a `.cube` file or a built-in look, after adjustments.
+    #[serde(default)]
+    pub lut: Option<LutSettings>,
+    /// Unsharp mask applied after resizing.
+    #[serde(default)]
+    pub sharpen: Option<SharpenSettings>,
+    /// Looks such as grayscale, duotone or grain, applied in order after sharpening.
+    #[serde(default)]
+    pub filters: Vec<Filter>,
     pub remove_bg_enabled: bool,
+    /// Crops transparent or uniform borders, after background removal.
+    #[serde(default)]
+    pub trim: Option<TrimSettings>,
+    /// Padding, border and rounded or circular mask, after trimming.
+    #[serde(default)]
+    pub canvas: Option<CanvasSettings>,
+    /// Captions and quote text, drawn in order after the canvas.
+    #[serde(default)]
+    pub text_layers: Vec<TextLayer>,
+    /// Logo or text overlay, drawn last so it sits on the final pixels.
+    #[serde(default)]
+    pub watermark: Option<WatermarkSettings>,
+    /// Composites transparency onto a background as the last step. With it
+    /// set, JPEG output is kept instead of switching to PNG.
+    #[serde(default)]
+    pub flatten: Option<FlattenSettings>,
+    /// Noise reduction at the start of the per-image steps, before resizing,
+    /// sharpening and any overlays.
+    #[serde(default)]
+    pub denoise: Option<DenoiseSettings>,
+    /// Reduces the output to a palette; PNG and GIF are written indexed.
+    /// Animations must go to GIF, since WebP frames would be re-encoded lossy.
+    #[serde(default)]
+    pub quantize: Option<QuantizeSettings>,
+    /// Outputs only this frame of an animated input as a still image.
+    #[serde(default)]
+    pub extract_frame: Option<usize>,
+    /// How many times animated output plays, 0 for forever. Follows the
+    /// source when unset.
+    #[serde(default)]
+    pub loop_count: Option<u16>,
+    /// Blurs, pixelates or fills regions of the source image, before any
+    /// other step so the coordinates match the original.
+    #[serde(default)]
+    pub redact: Option<RedactSettings>,
+    /// Detects faces with the local ONNX model and hides them like `redact`.
+    #[serde(default)]
+    pub face_blur: Option<FaceBlurSettings>,
+    #[serde(default)]
+    pub rotate: Option<RotateSettings>,
+    #[serde(default)]
+    pub flip: Option<FlipSettings>,
+    /// Warps a photographed page or whiteboard flat; corners refer to the
+    /// rotated and flipped image, like `crop`. Animations use the corners
+    /// found on their first frame.
+    #[serde(default)]
+    pub perspective: Option<PerspectiveSettings>,
+    /// Straightens tilted scans and photos, after the manual rotation. The
+    /// tilt of an animation is measured on its first frame.
+    #[serde(default)]
+    pub deskew: Option<DeskewSettings>,
+    #[serde(default)]
+    pub crop: Option<CropSettings>,
+    /// Crop to an aspect ratio around the most salient area, after `crop`.
+    #[serde(default)]
+    pub smart_crop: Option<SmartCropSettings>,
 }
 
 #[derive(Serialize)]
@@ -65,72 +173,342 @@ pub struct ProcessResult {
     pub width: u32,
     pub height: u32,
     pub size_bytes: u64,
+    pub frame_count: u32,
+    /// Area kept by the trim step, if it ran.
+    pub trim: Option<TrimRect>,
+    /// Window and focal point picked by the smart crop, if it ran.
+    pub smart_crop: Option<SmartCrop>,
+    /// Output size without the denoise step, when asked to report it.
+    pub size_without_denoise: Option<u64>,
+    /// Tilt corrected by deskew, in degrees, if it ran.
+    pub deskew_angle: Option<f32>,
 }
 
-fn run_pipeline(app: AppHandle, data_url: String, s: ProcessSettings) -> Result<ProcessResult> {
-    let (img, _) = decode_data_url(&data_url)?;
-
-    let img = if s.resize_enabled && s.resize_max_px > 0 {
-        let (orig_w, orig_h) = img.dimensions();
-        let max_w = s.resize_max_px;
-        let max_h = if s.resize_custom_h > 0 { s.resize_custom_h } else { s.resize_max_px };
-        let ratio = (max_w as f64 / orig_w as f64).min(max_h as f64 / orig_h as f64);
-        if ratio < 0.9999 {
-            let nw = ((orig_w as f64 * ratio) as u32).max(1);
-            let nh = ((orig_h as f64 * ratio) as u32).max(1);
-            img.resize_exact(nw, nh, FilterType::Lanczos3)
-        } else {
-            img
-        }
+/// Redaction, face blurring, rotation and flip, which run before the
+/// perspective corners and tilt are measured.
+fn orient_steps(img: DynamicImage, s: &ProcessSettings) -> Result<DynamicImage> {
+    let img = match &s.redact {
+        Some(redact) => apply_redact(img, redact)?,
+        None => img,
+    };
+    let img = match &s.face_blur {
+        Some(face_blur) => apply_face_blur(img, face_blur)?,
+        None => img,
+    };
+    let img = match &s.rotate {
+        Some(rotate) => apply_rotate(img, rotate)?,
+        None => img,
+    };
+    let img = match s.flip {
+        Some(flip) => apply_flip(img, flip),
+        None => img,
+    };
+    Ok(img)
+}
+
+/// Measurements taken once, on the first frame for animations, so every
+/// frame is straightened the same way.
+#[derive(Clone, Copy, Default)]
+struct Straighten {
+    corners: Option<[(f32, f32); 4]>,
+    skew: Option<f32>,
+}
+
+fn measure_straighten(img: &DynamicImage, s: &ProcessSettings) -> Result<Straighten> {
+    let corners = s.perspective.as_ref().map(|p| perspective_corners(img, p)).transpose()?;
+    let skew = match &s.deskew {
+        // The tilt is measured on the rectified image it will be applied to.
+        Some(deskew) => Some(match (&s.perspective, corners) {
+            (Some(perspective), Some(corners)) => {
+                let flat = apply_perspective_corners(img.clone(), corners, perspective)?;
+                detect_skew(&flat, deskew.max_angle)
+            }
+            _ => detect_skew(img, deskew.max_angle),
+        }),
+        None => None,
+    };
+    Ok(Straighten { corners, skew })
+}
+
+/// Perspective, deskew and crop, which run before the smart crop is measured.
+fn straighten_steps(img: DynamicImage, s: &ProcessSettings, m: Straighten) -> Result<DynamicImage> {
+    let img = match (&s.perspective, m.corners) {
+        (Some(perspective), Some(corners)) => apply_perspective_corners(img, corners, perspective)?,
+        _ => img,
+    };
+    let img = match (&s.deskew, m.skew) {
+        (Some(deskew), Some(angle)) => apply_deskew_angle(img, angle, deskew)?,
+        _ => img,
+    };
+    let img = match &s.crop {
+        Some(crop) => apply_crop(img, crop)?,
+        None => img,
+    };
+    Ok(img)
+}
+
+/// Applies denoise (passed separately so its effect on size can be
+/// measured), the smart crop and automatic corrections (measured on the
+/// first frame for animations, on the image itself when `auto` is unset)
+/// and every enabled step up to background removal to one oriented image
+/// or animation frame.
+fn apply_steps(
+    app: &AppHandle,
+    img: DynamicImage,
+    s: &ProcessSettings,
+    smart: Option<SmartCrop>,
+    auto: Option<AutoCorrection>,
+    denoise: Option<&DenoiseSettings>,
+) -> Result<DynamicImage> {
+    let img = match denoise {
+        Some(denoise) => apply_denoise(img, denoise),
+        None => img,
+    };
+
+    let img = match smart {
+        Some(crop) => apply_smart_crop(img, crop),
+        None => img,
+    };
+
+    let (before_w, before_h) = img.dimensions();
+    let img = if s.resize_enabled && (s.resize_max_px > 0 || s.resize_sizing.is_some()) {
+        let target = BoxResize {
+            width: s.resize_max_px,
+            height: if s.resize_custom_h > 0 { s.resize_custom_h } else { s.resize_max_px },
+            mode: s.resize_mode,
+            allow_upscale: s.resize_allow_upscale,
+            anchor: s.resize_anchor,
+            background: parse_background(s.resize_background.as_deref())?,
+            filter: s.resize_filter,
+        };
+        let target = match &s.resize_sizing {
+            Some(sizing) => sizing.target(img.width(), img.height(), target)?,
+            None => target,
+        };
+        resize_to_box(img, &target)?
     } else {
         img
     };
 
+    let img = match &s.adjust {
+        Some(adjust) => {
+            let auto = auto.unwrap_or_else(|| measure_auto_correction(&img, adjust));
+            apply_adjustments(img, adjust, auto)
+        }
+        None => img,
+    };
+
+    let img = match &s.lut {
+        Some(lut) => apply_lut(img, lut)?,
+        None => img,
+    };
+
+    let img = match &s.sharpen {
+        Some(sharpen) => {
+            let downscale = (before_w as f32 / img.width() as f32).max(before_h as f32 / img.height() as f32);
+            apply_sharpen(img, sharpen, downscale)
+        }
+        None => img,
+    };
+
+    let img = apply_filters(img, &s.filters)?;
+
     let img = if s.remove_bg_enabled {
-        apply_remove_bg(&app, img)?
+        apply_remove_bg(app, img)?
     } else {
         img
     };
 
-    let format = if s.remove_bg_enabled && s.format == "jpeg" {
+    Ok(img)
+}
+
+/// Steps that run once the content is final: the trim (measured beforehand,
+/// across all frames for animations), the canvas, text layers, the
+/// watermark (reused from `marks` while the size stays the same) and the
+/// flatten chosen by `run_pipeline`.
+fn finish_steps(
+    img: DynamicImage,
+    s: &ProcessSettings,
+    trim: Option<TrimRect>,
+    flatten: Option<&FlattenSettings>,
+    marks: &mut MarkCache,
+) -> Result<DynamicImage> {
+    let img = match trim {
+        Some(rect) => apply_trim_rect(img, rect),
+        None => img,
+    };
+
+    let img = match &s.canvas {
+        Some(canvas) => apply_canvas(img, canvas)?,
+        None => img,
+    };
+
+    let img = apply_text_layers(img, &s.text_layers)?;
+
+    let img = match &s.watermark {
+        Some(watermark) => apply_watermark(img, watermark, marks)?,
+        None => img,
+    };
+
+    let img = match flatten {
+        Some(flatten) => apply_flatten(img, flatten)?,
+        None => img,
+    };
+
+    Ok(img)
+}
+
+enum Source {
+    Still(DynamicImage),
+    Animated(Vec<Frame>, u16),
+}
+
+fn run_pipeline(app: AppHandle, data_url: String, s: ProcessSettings) -> Result<ProcessResult> {
+    let (bytes, source_format) = data_url_bytes(&data_url)?;
+
+    let source = match decode_frames(&bytes)? {
+        Some(animation) => match s.extract_frame {
+            Some(index) => Source::Still(pick_frame(animation.frames, index)?),
+            None => Source::Animated(animation.frames, animation.loop_count),
+        },
+        None => Source::Still(decode_bytes(&bytes, &source_format)?),
+    };
+
+    let canvas_alpha = match &s.canvas {
+        Some(canvas) => canvas.introduces_transparency()?,
+        None => false,
+    };
+    let format = if (s.remove_bg_enabled || canvas_alpha) && s.flatten.is_none() && s.format == "jpeg" {
         "png"
     } else {
         s.format.as_str()
     };
-    let quality = s.quality.clamp(1, 100);
-    let ext = match format { "png" => "png", "webp" => "webp", _ => "jpg" };
+    // JPEG can't store alpha, so anything still transparent goes onto white
+    // unless a background was chosen.
+    let flatten = match (&s.flatten, format) {
+        (Some(flatten), _) => Some(flatten.clone()),
+        (None, "jpeg") => Some(FlattenSettings::default()),
+        (None, _) => None,
+    };
+    check_jxl_quality(format, s.quality)?;
+    let quality = s.quality.unwrap_or(85).clamp(1, 100);
+    let ext = match format { "png" => "png", "webp" => "webp", "jxl" => "jxl", "gif" => "gif", _ => "jpg" };
+
+    if matches!(source, Source::Animated(..)) && !can_animate(format) {
+        return Err(PixoraError::Process(format!(
+            "Format {} can't store animation; use GIF or WebP, or extract a single frame",
+            format
+        )));
+    }
+    if matches!(source, Source::Animated(..)) && s.quantize.is_some() && format != "gif" {
+        return Err(PixoraError::Process(
+            "Quantized animations can only be written as GIF".to_string(),
+        ));
+    }
 
     let out_path = next_temp_path(&app, ext)?;
-    {
-        let file = std::fs::File::create(&out_path)?;
-        let mut writer = BufWriter::new(file);
-
-        match format {
-            "png"  => img.write_to(&mut writer, ImageFormat::Png)
-                         .map_err(|e| PixoraError::Image(e.to_string()))?,
-            "webp" => img.write_to(&mut writer, ImageFormat::WebP)
-                         .map_err(|e| PixoraError::Image(e.to_string()))?,
-            _ => {
-                let mut enc =
-                    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, quality);
-                enc.encode_image(&img).map_err(|e| PixoraError::Image(e.to_string()))?;
-            }
+    let mut marks = MarkCache::default();
+    let (width, height, frame_count, trim, smart, size_without_denoise, straighten) = match source {
+        Source::Animated(frames, source_loops) => {
+            let frames = map_frames(frames, |img| orient_steps(img, &s))?;
+            let straighten = measure_straighten(&DynamicImage::ImageRgba8(frames[0].buffer().clone()), &s)?;
+            let frames = map_frames(frames, |img| straighten_steps(img, &s, straighten))?;
+            let smart = match &s.smart_crop {
+                Some(settings) => {
+                    let first = DynamicImage::ImageRgba8(frames[0].buffer().clone());
+                    Some(find_smart_crop(&app, &first, settings)?)
+                }
+                None => None,
+            };
+            // Auto levels and white balance from the first frame, so they
+            // don't flicker.
+            let auto = match &s.adjust {
+                Some(adjust) => {
+                    let first = DynamicImage::ImageRgba8(frames[0].buffer().clone());
+                    let first = match smart {
+                        Some(crop) => apply_smart_crop(first, crop),
+                        None => first,
+                    };
+                    Some(measure_auto_correction(&first, adjust))
+                }
+                None => None,
+            };
+            let frames = map_frames(frames, |img| apply_steps(&app, img, &s, smart, auto, s.denoise.as_ref()))?;
+            let trim = s.trim.as_ref().map(|t| frames_trim_rect(&frames, t)).transpose()?;
+            let frames = map_frames(frames, |img| finish_steps(img, &s, trim, flatten.as_ref(), &mut marks))?;
+            let loop_count = s.loop_count.unwrap_or(source_loops);
+            // One palette from the first frame keeps colors from flickering;
+            // its transparent entry is reserved if any frame needs it.
+            let encoded = match &s.quantize {
+                Some(q) => {
+                    let transparent = frames.iter().any(|f| has_transparency(f.buffer()));
+                    let palette = build_palette(frames[0].buffer(), &q.palette, transparent)?;
+                    let indexed: Vec<_> = frames
+                        .iter()
+                        .map(|f| (quantize_with(f.buffer(), &palette, q.dither), f.delay()))
+                        .collect();
+                    encode_indexed_gif(&indexed, loop_count)?
+                }
+                None => encode_animation(&frames, format, quality, loop_count)?,
+            };
+            std::fs::write(&out_path, encoded)?;
+            let (width, height) = frames[0].buffer().dimensions();
+            (width, height, frames.len() as u32, trim, smart, None, straighten)
         }
-    }
+        Source::Still(img) => {
+            let img = orient_steps(img, &s)?;
+            let straighten = measure_straighten(&img, &s)?;
+            let img = straighten_steps(img, &s, straighten)?;
+            let smart = s.smart_crop.as_ref().map(|settings| find_smart_crop(&app, &img, settings)).transpose()?;
+            let mut process = |img: DynamicImage, denoise: Option<&DenoiseSettings>| -> Result<(DynamicImage, Option<TrimRect>)> {
+                let img = apply_steps(&app, img, &s, smart, None, denoise)?;
+                let trim = s.trim.as_ref().map(|t| trim_rect(&img.to_rgba8(), t)).transpose()?;
+                Ok((finish_steps(img, &s, trim, flatten.as_ref(), &mut marks)?, trim))
+            };
+            let without_denoise = s.denoise.as_ref().is_some_and(|d| d.report_size).then(|| img.clone());
+            let (img, trim) = process(img, s.denoise.as_ref())?;
+            let strip = s.redact.as_ref().is_some_and(|r| r.strip_metadata);
+            let dpi = if s.resize_enabled && !strip { s.resize_sizing.and_then(|sizing| sizing.dpi()) } else { None };
+            let encode = |img: &DynamicImage| -> Result<Vec<u8>> {
+                match &s.quantize {
+                    Some(q) => {
+                        let indexed = quantize(img, q)?;
+                        match format {
+                            "png" | "gif" => encode_indexed(&indexed, format, dpi),
+                            _ => encode_bytes(&indexed.to_image(), format, quality, dpi),
+                        }
+                    }
+                    None => encode_bytes(img, format, quality, dpi),
+                }
+            };
+            std::fs::write(&out_path, encode(&img)?)?;
+            // Same steps again without denoise, to show what it saved.
+            let size_without_denoise = match without_denoise {
+                Some(original) => Some(encode(&process(original, None)?.0)?.len() as u64),
+                None => None,
+            };
+            let (width, height) = img.dimensions();
+            (width, height, 1, trim, smart, size_without_denoise, straighten)
+        }
+    };
 
     let size_bytes = out_path.metadata()?.len();
-    let (width, height) = img.dimensions();
 
     Ok(ProcessResult {
         output_path: out_path.to_string_lossy().into_owned(),
         width,
         height,
         size_bytes,
+        frame_count,
+        trim,
+        smart_crop: smart,
+        size_without_denoise,
+        deskew_angle: straighten.skew,
     })
 }
 
 
-fn register_temp(state: &State<'_, PixoraState>, path: PathBuf) -> Result<()> {
+pub fn register_temp(state: &State<'_, PixoraState>, path: PathBuf) -> Result<()> {
     let mut files = state.temp_files.lock().map_err(|e| PixoraError::Lock(e.to_string()))?;
     if !files.contains(&path) {
         files.push(path);
@@ -169,7 +547,7 @@ pub async fn read_temp_as_data_url(state: State<'_, PixoraState>, path: String)
         }
     }
 
-    crate::commands::save::load_image_file(path).await
+    crate::commands::save::load_image_file(path, None).await
 }
 
 #[tauri::command]
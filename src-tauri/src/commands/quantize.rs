@@ -0,0 +1,388 @@
+use color_quant::NeuQuant;
+use image::{DynamicImage, RgbImage, RgbaImage};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::dpi::set_png_dpi;
+
+/// Alpha below this becomes the transparent palette entry.
+const ALPHA_CUTOFF: u8 = 128;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum PaletteSource {
+    /// Palette of `colors` entries (2–256) picked from the image.
+    Auto {
+        #[serde(default = "default_colors")]
+        colors: u16,
+    },
+    /// Fixed hex colors.
+    Colors { colors: Vec<String> },
+    /// GIMP `.gpl` palette file.
+    File { path: String },
+}
+
+fn default_colors() -> u16 {
+    16
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Dither {
+    None,
+    #[default]
+    FloydSteinberg,
+    /// 8×8 Bayer matrix; no error bleeding, so it suits animations and e-ink.
+    Ordered,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantizeSettings {
+    pub palette: PaletteSource,
+    #[serde(default)]
+    pub dither: Dither,
+}
+
+/// Palette-indexed pixels.
+pub struct Indexed {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 3]>,
+    pub indices: Vec<u8>,
+    /// Palette index used for transparent pixels, if any.
+    pub transparent: Option<u8>,
+}
+
+impl Indexed {
+    /// Expands back to RGB, or RGBA when there is a transparent entry.
+    pub fn to_image(&self) -> DynamicImage {
+        let color = |i: usize| self.palette[self.indices[i] as usize];
+        match self.transparent {
+            Some(t) => DynamicImage::ImageRgba8(RgbaImage::from_fn(self.width, self.height, |x, y| {
+                let i = (y * self.width + x) as usize;
+                let [r, g, b] = color(i);
+                image::Rgba([r, g, b, if self.indices[i] == t { 0 } else { 255 }])
+            })),
+            None => DynamicImage::ImageRgb8(RgbImage::from_fn(self.width, self.height, |x, y| {
+                image::Rgb(color((y * self.width + x) as usize))
+            })),
+        }
+    }
+}
+
+fn parse_gpl(path: &str) -> Result<Vec<[u8; 3]>> {
+    let text = std::fs::read_to_string(path)?;
+    let invalid = |line: &str| PixoraError::Process(format!("Invalid palette line in {}: {}", path, line));
+    let mut colors = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("GIMP Palette")
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let mut channel = || -> Result<u8> {
+            parts.next().and_then(|v| v.parse().ok()).ok_or_else(|| invalid(line))
+        };
+        colors.push([channel()?, channel()?, channel()?]);
+    }
+    Ok(colors)
+}
+
+/// Whether any pixel of `rgba` would map to the transparent entry.
+pub fn has_transparency(rgba: &RgbaImage) -> bool {
+    rgba.pixels().any(|p| p[3] < ALPHA_CUTOFF)
+}
+
+/// Resolves the palette, sampling `sample` for `auto`. With `transparent`
+/// set, one entry is left free for transparency; pass it when any image the
+/// palette will be used on has transparent pixels, not just `sample`.
+pub fn build_palette(sample: &RgbaImage, source: &PaletteSource, transparent: bool) -> Result<Vec<[u8; 3]>> {
+    let palette = match source {
+        PaletteSource::Auto { colors } => {
+            let max = if transparent { 255 } else { 256 };
+            let colors = (*colors as usize).clamp(2, max);
+            let pixels: Vec<u8> = sample
+                .pixels()
+                .filter(|p| p[3] >= ALPHA_CUTOFF)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect();
+            let pixels = if pixels.is_empty() { vec![0, 0, 0, 255] } else { pixels };
+            NeuQuant::new(10, colors, &pixels)
+                .color_map_rgb()
+                .chunks_exact(3)
+                .map(|c| [c[0], c[1], c[2]])
+                .collect()
+        }
+        PaletteSource::Colors { colors } => colors
+            .iter()
+            .map(|hex| parse_hex_color(hex).map(|c| [c[0], c[1], c[2]]))
+            .collect::<Result<Vec<_>>>()?,
+        PaletteSource::File { path } => parse_gpl(path)?,
+    };
+
+    if palette.is_empty() {
+        return Err(PixoraError::Process("Palette has no colors".to_string()));
+    }
+    let max = if transparent { 255 } else { 256 };
+    if palette.len() > max {
+        return Err(PixoraError::Process(format!(
+            "Palette has {} colors; at most {} fit{}",
+            palette.len(),
+            max,
+            if transparent { " alongside transparency" } else { "" }
+        )));
+    }
+    Ok(palette)
+}
+
+/// Nearest palette entry by weighted RGB distance, memoized per exact
+/// 8-bit color since images and dithering revisit the same colors often.
+struct Matcher<'a> {
+    palette: &'a [[u8; 3]],
+    cache: HashMap<[u8; 3], u8>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(palette: &'a [[u8; 3]]) -> Self {
+        Matcher { palette, cache: HashMap::new() }
+    }
+
+    fn nearest(&mut self, rgb: [f32; 3]) -> u8 {
+        let c = rgb.map(|v| v.round().clamp(0.0, 255.0) as u8);
+        let palette = self.palette;
+        *self.cache.entry(c).or_insert_with(|| {
+            palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| {
+                    let d = |i: usize| (p[i] as i32 - c[i] as i32).pow(2);
+                    2 * d(0) + 4 * d(1) + 3 * d(2)
+                })
+                .map(|(i, _)| i as u8)
+                .unwrap_or(0)
+        })
+    }
+}
+
+const BAYER_8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Maps `rgba` onto `palette` with the chosen dithering.
+pub fn quantize_with(rgba: &RgbaImage, palette: &[[u8; 3]], dither: Dither) -> Indexed {
+    let (width, height) = rgba.dimensions();
+    let transparent = (palette.len() < 256 && has_transparency(rgba)).then_some(palette.len() as u8);
+    let mut full_palette = palette.to_vec();
+    if transparent.is_some() {
+        full_palette.push([0, 0, 0]);
+    }
+
+    let mut matcher = Matcher::new(palette);
+    let mut indices = vec![0u8; (width * height) as usize];
+    let is_clear = |x: u32, y: u32| transparent.is_some() && rgba.get_pixel(x, y)[3] < ALPHA_CUTOFF;
+
+    match dither {
+        Dither::None | Dither::Ordered => {
+            // Threshold spread roughly matches the gap between palette colors.
+            let spread = 255.0 / (palette.len() as f32).cbrt().max(1.0);
+            for (x, y, p) in rgba.enumerate_pixels() {
+                let i = (y * width + x) as usize;
+                if is_clear(x, y) {
+                    indices[i] = transparent.unwrap_or(0);
+                    continue;
+                }
+                let offset = if dither == Dither::Ordered {
+                    (BAYER_8[(y % 8) as usize][(x % 8) as usize] as f32 + 0.5) / 64.0 - 0.5
+                } else {
+                    0.0
+                };
+                indices[i] = matcher.nearest([0, 1, 2].map(|c| p[c] as f32 + offset * spread));
+            }
+        }
+        Dither::FloydSteinberg => {
+            // Serpentine scan with two rows of accumulated error.
+            let w = width as usize;
+            let mut current = vec![[0f32; 3]; w + 2];
+            let mut next = vec![[0f32; 3]; w + 2];
+            for y in 0..height {
+                let forward = y % 2 == 0;
+                for step in 0..w {
+                    let x = if forward { step } else { w - 1 - step };
+                    let i = y as usize * w + x;
+                    if is_clear(x as u32, y) {
+                        indices[i] = transparent.unwrap_or(0);
+                        continue;
+                    }
+                    let p = rgba.get_pixel(x as u32, y);
+                    let want = [0, 1, 2].map(|c| p[c] as f32 + current[x + 1][c]);
+                    let index = matcher.nearest(want);
+                    indices[i] = index;
+                    let got = palette[index as usize];
+                    let (ahead, behind) = if forward { (x + 2, x) } else { (x, x + 2) };
+                    for c in 0..3 {
+                        let err = want[c] - got[c] as f32;
+                        current[ahead][c] += err * 7.0 / 16.0;
+                        next[behind][c] += err * 3.0 / 16.0;
+                        next[x + 1][c] += err * 5.0 / 16.0;
+                        next[ahead][c] += err * 1.0 / 16.0;
+                    }
+                }
+                std::mem::swap(&mut current, &mut next);
+                next.iter_mut().for_each(|e| *e = [0.0; 3]);
+            }
+        }
+    }
+
+    Indexed { width, height, palette: full_palette, indices, transparent }
+}
+
+pub fn quantize(img: &DynamicImage, settings: &QuantizeSettings) -> Result<Indexed> {
+    let rgba = img.to_rgba8();
+    let palette = build_palette(&rgba, &settings.palette, has_transparency(&rgba))?;
+    Ok(quantize_with(&rgba, &palette, settings.dither))
+}
+
+/// Smallest PNG bit depth that holds `colors` palette entries.
+fn png_depth(colors: usize) -> (png::BitDepth, u32) {
+    match colors {
+        0..=2 => (png::BitDepth::One, 1),
+        3..=4 => (png::BitDepth::Two, 2),
+        5..=16 => (png::BitDepth::Four, 4),
+        _ => (png::BitDepth::Eight, 8),
+    }
+}
+
+/// Writes a palette PNG (packed to 1, 2 or 4 bits for small palettes) or
+/// a single-frame GIF straight from the indices, so no colors are re-picked.
+pub fn encode_indexed(indexed: &Indexed, format: &str, dpi: Option<f32>) -> Result<Vec<u8>> {
+    let flat_palette: Vec<u8> = indexed.palette.iter().flatten().copied().collect();
+    match format {
+        "png" => {
+            let (depth, bits) = png_depth(indexed.palette.len());
+            let row_bytes = (indexed.width * bits).div_ceil(8) as usize;
+            let mut data = vec![0u8; row_bytes * indexed.height as usize];
+            for (i, &index) in indexed.indices.iter().enumerate() {
+                let (x, y) = (i % indexed.width as usize, i / indexed.width as usize);
+                let bit = x * bits as usize;
+                let shift = 8 - bits as usize - bit % 8;
+                data[y * row_bytes + bit / 8] |= index << shift;
+            }
+
+            let mut buf = Vec::new();
+            {
+                let mut encoder = png::Encoder::new(&mut buf, indexed.width, indexed.height);
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(depth);
+                encoder.set_palette(flat_palette);
+                if let Some(t) = indexed.transparent {
+                    let mut trns = vec![255u8; t as usize + 1];
+                    trns[t as usize] = 0;
+                    encoder.set_trns(trns);
+                }
+                let mut writer = encoder.write_header().map_err(|e| PixoraError::Image(e.to_string()))?;
+                writer.write_image_data(&data).map_err(|e| PixoraError::Image(e.to_string()))?;
+            }
+            Ok(match dpi {
+                Some(dpi) => set_png_dpi(buf, dpi),
+                None => buf,
+            })
+        }
+        "gif" => {
+            if indexed.width > u16::MAX as u32 || indexed.height > u16::MAX as u32 {
+                return Err(PixoraError::Process("Image too large for GIF".to_string()));
+            }
+            // GIF palettes hold a power of two entries.
+            let entries = indexed.palette.len().next_power_of_two().max(2);
+            let mut palette = flat_palette;
+            palette.resize(entries * 3, 0);
+            let mut buf = Vec::new();
+            {
+                let mut enc = gif::Encoder::new(&mut buf, indexed.width as u16, indexed.height as u16, &palette)
+                    .map_err(|e| PixoraError::Image(e.to_string()))?;
+                let frame = gif::Frame {
+                    width: indexed.width as u16,
+                    height: indexed.height as u16,
+                    transparent: indexed.transparent,
+                    buffer: Cow::Borrowed(&indexed.indices),
+                    ..Default::default()
+                };
+                enc.write_frame(&frame).map_err(|e| PixoraError::Image(e.to_string()))?;
+            }
+            Ok(buf)
+        }
+        _ => Err(PixoraError::Process(format!("Format {} has no indexed mode", format))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("pixora-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn parses_gpl_files() {
+        let path = write_temp(
+            "ok.gpl",
+            "GIMP Palette\nName: Test\nColumns: 2\n#\n  0   0   0\tBlack\n255 128 7 Orange\n\n",
+        );
+        assert_eq!(parse_gpl(&path).unwrap(), vec![[0, 0, 0], [255, 128, 7]]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_gpl_lines() {
+        for (i, line) in ["255 0", "256 0 0", "red green blue"].iter().enumerate() {
+            let path = write_temp(&format!("bad{}.gpl", i), &format!("GIMP Palette\n{}\n", line));
+            assert!(parse_gpl(&path).is_err(), "{}", line);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn fixed_palettes_match_exactly() {
+        // Colors a step apart used to share one cache cell.
+        let palette = [[0, 0, 0], [100, 100, 100], [101, 101, 101], [255, 255, 255]];
+        let mut matcher = Matcher::new(&palette);
+        assert_eq!(matcher.nearest([100.0; 3]), 1);
+        assert_eq!(matcher.nearest([101.0; 3]), 2);
+        assert_eq!(matcher.nearest([100.4; 3]), 1);
+        assert_eq!(matcher.nearest([-20.0, 0.0, 10.0]), 0);
+        assert_eq!(matcher.nearest([200.0, 255.0, 300.0]), 3);
+    }
+
+    #[test]
+    fn reserves_an_entry_for_transparency() {
+        let colors: Vec<String> = (0..256).map(|i| format!("#{:02x}0000", i)).collect();
+        let source = PaletteSource::Colors { colors };
+        let sample = RgbaImage::new(1, 1);
+        assert_eq!(build_palette(&sample, &source, false).unwrap().len(), 256);
+        assert!(build_palette(&sample, &source, true).is_err());
+
+        let mut rgba = RgbaImage::from_pixel(2, 1, image::Rgba([250, 0, 0, 255]));
+        rgba.put_pixel(1, 0, image::Rgba([0, 0, 0, 0]));
+        let indexed = quantize_with(&rgba, &[[0, 0, 0], [255, 0, 0]], Dither::None);
+        assert_eq!((indexed.indices.clone(), indexed.transparent), (vec![1, 2], Some(2)));
+        assert_eq!(indexed.to_image().to_rgba8().get_pixel(1, 0)[3], 0);
+    }
+}
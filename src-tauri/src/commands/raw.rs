@@ -0,0 +1,217 @@
+use image::metadata::Orientation;
+use image::{DynamicImage, RgbImage};
+use rawloader::{RawImage, RawImageData, CFA};
+use std::io::Cursor;
+
+use crate::error::{PixoraError, Result};
+
+/// Camera RAW extensions accepted on import, with the MIME type used in their data URLs.
+pub const RAW_FORMATS: [(&str, &str); 10] = [
+    ("dng", "image/x-adobe-dng"),
+    ("cr2", "image/x-canon-cr2"),
+    ("nef", "image/x-nikon-nef"),
+    ("nrw", "image/x-nikon-nrw"),
+    ("arw", "image/x-sony-arw"),
+    ("orf", "image/x-olympus-orf"),
+    ("rw2", "image/x-panasonic-rw2"),
+    ("raf", "image/x-fuji-raf"),
+    ("pef", "image/x-pentax-pef"),
+    ("srw", "image/x-samsung-srw"),
+];
+
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+pub fn raw_mime(ext: &str) -> Option<&'static str> {
+    RAW_FORMATS.iter().find(|(e, _)| *e == ext).map(|(_, mime)| *mime)
+}
+
+pub fn is_raw_header(header: &str) -> bool {
+    RAW_FORMATS.iter().any(|(_, mime)| header.contains(mime))
+}
+
+/// Settings for the RAW develop step. The defaults give a neutral,
+/// camera-white-balanced sRGB rendering.
+#[derive(Clone, Copy, Default)]
+pub struct RawDevelop {
+    /// Exposure compensation in stops.
+    pub exposure_ev: f32,
+}
+
+/// Decodes a camera RAW file and develops it with the default settings.
+pub fn decode_raw(bytes: &[u8]) -> Result<DynamicImage> {
+    develop_raw(bytes, RawDevelop::default())
+}
+
+/// Decodes a camera RAW file and develops it to an 8-bit sRGB image:
+/// black/white level scaling, as-shot white balance, bilinear demosaic,
+/// camera-to-sRGB color matrix, exposure and the sRGB transfer curve.
+pub fn develop_raw(bytes: &[u8], develop: RawDevelop) -> Result<DynamicImage> {
+    let raw = rawloader::decode(&mut Cursor::new(bytes))
+        .map_err(|e| PixoraError::Image(format!("RAW decode failed: {}", e)))?;
+
+    let [top, right, bottom, left] = raw.crops;
+    let width = raw.width.saturating_sub(left + right);
+    let height = raw.height.saturating_sub(top + bottom);
+    if width == 0 || height == 0 {
+        return Err(PixoraError::Image("RAW image has no usable area".to_string()));
+    }
+
+    let linear = if raw.cpp == 3 {
+        scale_rgb(&raw, width, height)
+    } else if raw.is_monochrome() {
+        scale_mono(&raw, width, height)
+    } else {
+        demosaic(&raw, width, height)
+    };
+
+    let matrix = cam_to_srgb(&raw);
+    let gain = 2f32.powf(develop.exposure_ev);
+    let lut = srgb_lut();
+
+    let mut out = RgbImage::new(width as u32, height as u32);
+    for (px, cam) in out.pixels_mut().zip(linear.iter()) {
+        for c in 0..3 {
+            let v = (matrix[c][0] * cam[0] + matrix[c][1] * cam[1] + matrix[c][2] * cam[2]) * gain;
+            px[c] = lut[(v.clamp(0.0, 1.0) * (lut.len() - 1) as f32) as usize];
+        }
+    }
+
+    let mut img = DynamicImage::ImageRgb8(out);
+    if let Some(orientation) = Orientation::from_exif(raw.orientation.to_u16() as u8) {
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
+}
+
+/// Returns sensel `index` of the full sensor grid, scaled
+/// so black is 0.0 and the white level is 1.0 for its color channel.
+fn sensel(raw: &RawImage, index: usize, channel: usize) -> f32 {
+    match &raw.data {
+        RawImageData::Integer(data) => {
+            let black = raw.blacklevels[channel] as f32;
+            let white = raw.whitelevels[channel] as f32;
+            ((data[index] as f32 - black) / (white - black).max(1.0)).max(0.0)
+        }
+        RawImageData::Float(data) => data[index].max(0.0),
+    }
+}
+
+fn white_balance(raw: &RawImage) -> [f32; 3] {
+    let wb = raw.wb_coeffs;
+    let wb = if wb[..3].iter().all(|c| c.is_finite() && *c > 0.0) { wb } else { raw.neutralwb() };
+    [wb[0] / wb[1], 1.0, wb[2] / wb[1]]
+}
+
+/// Folds the fourth CFA color (the second green of RGBE sensors) into green.
+fn rgb_channel(color: usize) -> usize {
+    if color == 3 { 1 } else { color }
+}
+
+fn demosaic(raw: &RawImage, width: usize, height: usize) -> Vec<[f32; 3]> {
+    let [top, _, _, left] = raw.crops;
+    let cfa: CFA = raw.cropped_cfa();
+    let wb = white_balance(raw);
+
+    let mut plane = vec![0f32; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let color = cfa.color_at(y, x);
+            let index = (y + top) * raw.width + x + left;
+            plane[y * width + x] = sensel(raw, index, color) * wb[rgb_channel(color)];
+        }
+    }
+
+    let mut out = vec![[0f32; 3]; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let own = rgb_channel(cfa.color_at(y, x));
+            let mut sum = [0f32; 3];
+            let mut count = [0u32; 3];
+            for yy in y.saturating_sub(1)..(y + 2).min(height) {
+                for xx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let c = rgb_channel(cfa.color_at(yy, xx));
+                    sum[c] += plane[yy * width + xx];
+                    count[c] += 1;
+                }
+            }
+            let px = &mut out[y * width + x];
+            for c in 0..3 {
+                px[c] = if c == own {
+                    plane[y * width + x]
+                } else if count[c] > 0 {
+                    sum[c] / count[c] as f32
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+    out
+}
+
+fn scale_rgb(raw: &RawImage, width: usize, height: usize) -> Vec<[f32; 3]> {
+    let [top, _, _, left] = raw.crops;
+    let wb = white_balance(raw);
+    let mut out = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let base = ((y + top) * raw.width + x + left) * 3;
+            out.push([
+                sensel(raw, base, 0) * wb[0],
+                sensel(raw, base + 1, 1) * wb[1],
+                sensel(raw, base + 2, 2) * wb[2],
+            ]);
+        }
+    }
+    out
+}
+
+fn scale_mono(raw: &RawImage, width: usize, height: usize) -> Vec<[f32; 3]> {
+    let [top, _, _, left] = raw.crops;
+    let mut out = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let v = sensel(raw, (y + top) * raw.width + x + left, 0);
+            out.push([v, v, v]);
+        }
+    }
+    out
+}
+
+/// Camera RGB to linear sRGB, with rows normalized so white-balanced
+/// neutrals stay neutral. Falls back to identity when the camera has no
+/// color matrix or is monochrome.
+fn cam_to_srgb(raw: &RawImage) -> [[f32; 3]; 3] {
+    let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    if raw.is_monochrome() || raw.xyz_to_cam.iter().flatten().all(|v| *v == 0.0) {
+        return identity;
+    }
+
+    let cam_to_xyz = raw.cam_to_xyz_normalized();
+    let mut m = [[0f32; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..3).map(|k| XYZ_TO_SRGB[i][k] * cam_to_xyz[k][j]).sum();
+        }
+        let sum: f32 = row.iter().sum();
+        if sum.abs() < f32::EPSILON {
+            return identity;
+        }
+        row.iter_mut().for_each(|v| *v /= sum);
+    }
+    m
+}
+
+fn srgb_lut() -> Vec<u8> {
+    (0..=u16::MAX)
+        .map(|i| {
+            let v = i as f32 / u16::MAX as f32;
+            let encoded = if v <= 0.0031308 { 12.92 * v } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+            (encoded * 255.0).round() as u8
+        })
+        .collect()
+}
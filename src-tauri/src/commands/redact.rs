@@ -0,0 +1,212 @@
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use imageproc::filter::gaussian_blur_f32;
+use serde::Deserialize;
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::transform::{transform_standalone, TransformResult};
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum RegionShape {
+    #[default]
+    Rect,
+    /// Ellipse inscribed in the rectangle.
+    Ellipse,
+}
+
+/// Area to hide, as fractions (0.0–1.0) of the source size.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub shape: RegionShape,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum RedactEffect {
+    /// Gaussian blur with a sigma of `strength` percent of the region's
+    /// shorter side.
+    Blur {
+        #[serde(default = "default_blur")]
+        strength: f32,
+    },
+    /// Blocks of `size` percent of the region's longer side.
+    Pixelate {
+        #[serde(default = "default_block")]
+        size: f32,
+    },
+    /// Solid hex color, black by default.
+    Fill {
+        #[serde(default)]
+        color: Option<String>,
+    },
+}
+
+impl Default for RedactEffect {
+    fn default() -> Self {
+        RedactEffect::Pixelate { size: default_block() }
+    }
+}
+
+fn default_blur() -> f32 {
+    15.0
+}
+
+fn default_block() -> f32 {
+    12.0
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactSettings {
+    pub regions: Vec<RedactRegion>,
+    #[serde(default)]
+    pub effect: RedactEffect,
+    /// Write no metadata at all (EXIF, resolution) into the output, so
+    /// nothing about the original travels with the redacted file.
+    #[serde(default)]
+    pub strip_metadata: bool,
+}
+
+/// A region in pixels, clamped to the image.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub shape: RegionShape,
+}
+
+impl PixelRegion {
+    fn contains(&self, x: u32, y: u32) -> bool {
+        match self.shape {
+            RegionShape::Rect => true,
+            RegionShape::Ellipse => {
+                let rx = self.width as f32 / 2.0;
+                let ry = self.height as f32 / 2.0;
+                let dx = (x - self.x) as f32 + 0.5 - rx;
+                let dy = (y - self.y) as f32 + 0.5 - ry;
+                (dx / rx).powi(2) + (dy / ry).powi(2) <= 1.0
+            }
+        }
+    }
+}
+
+impl RedactRegion {
+    fn to_pixels(self, width: u32, height: u32) -> Option<PixelRegion> {
+        let x = (self.x.clamp(0.0, 1.0) * width as f32).round() as u32;
+        let y = (self.y.clamp(0.0, 1.0) * height as f32).round() as u32;
+        let right = ((self.x + self.width).clamp(0.0, 1.0) * width as f32).round() as u32;
+        let bottom = ((self.y + self.height).clamp(0.0, 1.0) * height as f32).round() as u32;
+        if right <= x || bottom <= y {
+            return None;
+        }
+        Some(PixelRegion { x, y, width: right - x, height: bottom - y, shape: self.shape })
+    }
+}
+
+/// Builds the replacement pixels for `region`, the same size as the region.
+fn redacted_patch(rgba: &RgbaImage, region: &PixelRegion, effect: &RedactEffect) -> Result<RgbaImage> {
+    let (w, h) = (region.width, region.height);
+    match effect {
+        RedactEffect::Blur { strength } => {
+            let sigma = (w.min(h) as f32 * strength.max(0.0) / 100.0).max(1.0);
+            // Blur with a margin so the edges mix in real surroundings rather
+            // than the clamped border of the patch.
+            let margin = (sigma * 3.0).ceil() as u32;
+            let mx = region.x.saturating_sub(margin);
+            let my = region.y.saturating_sub(margin);
+            let mw = (region.x + w + margin).min(rgba.width()) - mx;
+            let mh = (region.y + h + margin).min(rgba.height()) - my;
+            let area = imageops::crop_imm(rgba, mx, my, mw, mh).to_image();
+            let blurred = gaussian_blur_f32(&area, sigma);
+            Ok(imageops::crop_imm(&blurred, region.x - mx, region.y - my, w, h).to_image())
+        }
+        RedactEffect::Pixelate { size } => {
+            let block = ((w.max(h) as f32 * size.clamp(0.0, 100.0) / 100.0).round() as u32).max(2);
+            let area = imageops::crop_imm(rgba, region.x, region.y, w, h).to_image();
+            let mut patch = RgbaImage::new(w, h);
+            for by in (0..h).step_by(block as usize) {
+                for bx in (0..w).step_by(block as usize) {
+                    let (bw, bh) = (block.min(w - bx), block.min(h - by));
+                    let mut sum = [0u64; 4];
+                    for y in by..by + bh {
+                        for x in bx..bx + bw {
+                            let px = area.get_pixel(x, y);
+                            for c in 0..4 {
+                                sum[c] += px[c] as u64;
+                            }
+                        }
+                    }
+                    let n = (bw * bh) as u64;
+                    let mean = Rgba(sum.map(|s| (s / n) as u8));
+                    for y in by..by + bh {
+                        for x in bx..bx + bw {
+                            patch.put_pixel(x, y, mean);
+                        }
+                    }
+                }
+            }
+            Ok(patch)
+        }
+        RedactEffect::Fill { color } => {
+            let color = match color.as_deref() {
+                Some(hex) => parse_hex_color(hex)?,
+                None => Rgba([0, 0, 0, 255]),
+            };
+            Ok(RgbaImage::from_pixel(w, h, color))
+        }
+    }
+}
+
+/// Hides each region of `rgba` in place.
+pub fn redact_regions(rgba: &mut RgbaImage, regions: &[PixelRegion], effect: &RedactEffect) -> Result<()> {
+    for region in regions {
+        let patch = redacted_patch(rgba, region, effect)?;
+        for (px, py, src) in patch.enumerate_pixels() {
+            let (x, y) = (region.x + px, region.y + py);
+            if region.contains(x, y) {
+                rgba.put_pixel(x, y, *src);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn apply_redact(img: DynamicImage, settings: &RedactSettings) -> Result<DynamicImage> {
+    let (width, height) = (img.width(), img.height());
+    let regions: Vec<PixelRegion> = settings.regions.iter().filter_map(|r| r.to_pixels(width, height)).collect();
+    if regions.is_empty() {
+        return Ok(img);
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    redact_regions(&mut rgba, &regions, &settings.effect)?;
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
+
+/// Redacts a single image. The output is encoded from pixels only, so it
+/// never carries the source's metadata.
+#[tauri::command]
+pub async fn redact_image(
+    data_url: String,
+    redact: RedactSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<TransformResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        transform_standalone(data_url, format, quality, |img| apply_redact(img, &redact))
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
@@ -1,5 +1,5 @@
 use base64::{engine::general_purpose, Engine as _};
-use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbaImage};
+use image::{imageops::FilterType, DynamicImage, GrayImage, ImageFormat, Luma, RgbaImage};
 use ndarray::Array;
 use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
@@ -40,7 +40,8 @@ struct ResourceEntry {
     size: usize,
 }
 
-fn model_path() -> Result<PathBuf> {
+/// Local data directory where ONNX models are kept.
+pub fn model_dir() -> Result<PathBuf> {
     let dir = dirs::data_local_dir()
         .or_else(dirs::cache_dir)
         .ok_or_else(|| PixoraError::Process("Could not determine cache directory".to_string()))?;
@@ -48,7 +49,11 @@ fn model_path() -> Result<PathBuf> {
     if !model_dir.exists() {
         std::fs::create_dir_all(&model_dir)?;
     }
-    Ok(model_dir.join("isnet_quint8.onnx"))
+    Ok(model_dir)
+}
+
+fn model_path() -> Result<PathBuf> {
+    Ok(model_dir()?.join("isnet_quint8.onnx"))
 }
 
 fn download_model_chunked() -> Result<Vec<u8>> {
@@ -108,11 +113,15 @@ fn create_session(app: &AppHandle) -> Result<Session> {
         data
     };
 
+    build_session(&model_data)
+}
+
+pub fn build_session(model_data: &[u8]) -> Result<Session> {
     let session = Session::builder()
         .map_err(|e: ort::Error| PixoraError::Process(e.to_string()))?
         .with_optimization_level(GraphOptimizationLevel::Level1)
         .map_err(|e: ort::Error| PixoraError::Process(e.to_string()))?
-        .commit_from_memory(&model_data)
+        .commit_from_memory(model_data)
         .map_err(|e: ort::Error| PixoraError::Process(format!("Failed to load ONNX model: {}", e)))?;
     Ok(session)
 }
@@ -137,15 +146,12 @@ fn encode_png_data_url(img: &RgbaImage) -> Result<String> {
     Ok(format!("data:image/png;base64,{}", b64))
 }
 
-pub fn apply_remove_bg_sync(app: &AppHandle, img: DynamicImage) -> Result<DynamicImage> {
+/// Runs ISNet on an image already scaled to `RESOLUTION`², returning the
+/// foreground probability at that size.
+fn predict_mask(app: &AppHandle, resized: &RgbaImage) -> Result<GrayImage> {
     let mut guard = ensure_session(app)?;
     let session = guard.as_mut().unwrap();
 
-    let rgba = img.to_rgba8();
-    let (orig_w, orig_h) = rgba.dimensions();
-
-    let resized = image::imageops::resize(&rgba, RESOLUTION, RESOLUTION, FilterType::Triangle);
-
     let input_array = Array::from_shape_fn(
         (1, 3, RESOLUTION as usize, RESOLUTION as usize),
         |(_n, c, h, w)| {
@@ -166,22 +172,44 @@ pub fn apply_remove_bg_sync(app: &AppHandle, img: DynamicImage) -> Result<Dynami
         .try_extract_array::<f32>()
         .map_err(|e: ort::Error| PixoraError::Process(format!("Failed to extract output: {}", e)))?;
 
-    let mut result_rgba = resized.clone();
-    for y in 0..RESOLUTION {
-        for x in 0..RESOLUTION {
-            let alpha = if mask_view.ndim() == 4 {
-                mask_view[[0, 0, y as usize, x as usize]]
-            } else {
-                mask_view[[0, y as usize, x as usize]]
-            };
-            result_rgba.get_pixel_mut(x, y)[3] = (alpha.clamp(0.0, 1.0) * 255.0) as u8;
-        }
+    Ok(GrayImage::from_fn(RESOLUTION, RESOLUTION, |x, y| {
+        let alpha = if mask_view.ndim() == 4 {
+            mask_view[[0, 0, y as usize, x as usize]]
+        } else {
+            mask_view[[0, y as usize, x as usize]]
+        };
+        Luma([(alpha.clamp(0.0, 1.0) * 255.0) as u8])
+    }))
+}
+
+pub fn apply_remove_bg_sync(app: &AppHandle, img: DynamicImage) -> Result<DynamicImage> {
+    let rgba = img.to_rgba8();
+    let (orig_w, orig_h) = rgba.dimensions();
+
+    let resized = image::imageops::resize(&rgba, RESOLUTION, RESOLUTION, FilterType::Triangle);
+    let mask = predict_mask(app, &resized)?;
+
+    let mut result_rgba = resized;
+    for (px, m) in result_rgba.pixels_mut().zip(mask.pixels()) {
+        px[3] = m[0];
     }
 
     let output_img = image::imageops::resize(&result_rgba, orig_w, orig_h, FilterType::Triangle);
     Ok(DynamicImage::ImageRgba8(output_img))
 }
 
+/// Whether the ISNet model is already on disk, so using it won't start a download.
+pub fn model_available() -> bool {
+    model_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+/// ISNet foreground mask of `img`, scaled to `width`×`height`.
+pub fn foreground_mask(app: &AppHandle, img: &DynamicImage, width: u32, height: u32) -> Result<GrayImage> {
+    let resized = image::imageops::resize(&img.to_rgba8(), RESOLUTION, RESOLUTION, FilterType::Triangle);
+    let mask = predict_mask(app, &resized)?;
+    Ok(image::imageops::resize(&mask, width, height, FilterType::Triangle))
+}
+
 pub fn apply_remove_bg(app: &AppHandle, img: DynamicImage) -> Result<DynamicImage> {
     apply_remove_bg_sync(app, img)
 }
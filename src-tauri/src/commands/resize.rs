@@ -1,9 +1,141 @@
 use base64::{engine::general_purpose, Engine as _};
-use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{imageops, DynamicImage, GenericImageView, ImageEncoder, ImageFormat, Rgba, RgbaImage};
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
+use crate::color::parse_hex_color;
 use crate::error::{PixoraError, Result};
 
+use super::animation::{decode_frames, encode_animation, map_frames};
+use super::dpi::{resolution_exif, set_png_dpi, CM_PER_INCH};
+use super::jxl::{check_jxl_quality, decode_jxl, encode_jxl, is_jxl};
+use super::raw::{decode_raw, is_raw_header};
+use super::resample::{resample, ResampleFilter};
+use super::svg::{rasterize_svg, SvgRasterOptions};
+use super::transform::Anchor;
+
+/// How an image is fitted into a target box.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ResizeMode {
+    /// Scale to fit inside the box; output may be smaller on one side.
+    #[default]
+    Fit,
+    /// Scale to fill the box and crop the overflow at the anchor.
+    Cover,
+    /// Scale to fit, then pad to the exact box with the background color.
+    Contain,
+    /// Stretch to the box, ignoring aspect ratio.
+    Exact,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LengthUnit {
+    #[default]
+    Cm,
+    Mm,
+    In,
+}
+
+impl LengthUnit {
+    fn to_inches(self, value: f32) -> f32 {
+        match self {
+            LengthUnit::Cm => value / CM_PER_INCH,
+            LengthUnit::Mm => value / (CM_PER_INCH * 10.0),
+            LengthUnit::In => value,
+        }
+    }
+}
+
+/// Target size expressed other than in pixels.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase", tag = "by")]
+pub enum ResizeSizing {
+    /// Scales both sides, e.g. 50 for half size.
+    Percent { percent: f32 },
+    /// Scales down so the image has at most this many million pixels.
+    Megapixels { megapixels: f32 },
+    /// Print size at `dpi`. With one side set the other follows the aspect
+    /// ratio; with both set the resize mode decides how to fill the box.
+    /// The DPI is written into the output's resolution metadata.
+    Physical {
+        width: Option<f32>,
+        height: Option<f32>,
+        #[serde(default)]
+        unit: LengthUnit,
+        dpi: f32,
+    },
+}
+
+impl ResizeSizing {
+    /// Box for this sizing. `template` provides the mode, anchor, upscale
+    /// and padding rules used when a physical box is given on both sides.
+    pub fn target(&self, orig_w: u32, orig_h: u32, template: BoxResize) -> Result<BoxResize> {
+        let exact = |scale: f64| BoxResize {
+            width: scaled(orig_w, scale),
+            height: scaled(orig_h, scale),
+            mode: ResizeMode::Exact,
+            ..template
+        };
+
+        match *self {
+            ResizeSizing::Percent { percent } => {
+                if percent <= 0.0 {
+                    return Err(PixoraError::Process(format!("Invalid resize percentage: {}", percent)));
+                }
+                Ok(exact(percent as f64 / 100.0))
+            }
+            ResizeSizing::Megapixels { megapixels } => {
+                if megapixels <= 0.0 {
+                    return Err(PixoraError::Process(format!("Invalid megapixel limit: {}", megapixels)));
+                }
+                let pixels = orig_w as f64 * orig_h as f64;
+                let scale = (megapixels as f64 * 1_000_000.0 / pixels).sqrt().min(1.0);
+                Ok(exact(scale))
+            }
+            ResizeSizing::Physical { width, height, unit, dpi } => {
+                if dpi <= 0.0 {
+                    return Err(PixoraError::Process(format!("Invalid DPI: {}", dpi)));
+                }
+                let px = |v: f32| ((unit.to_inches(v) * dpi).round() as u32).max(1);
+                let (width, height, mode) = match (width, height) {
+                    (Some(w), Some(h)) => (px(w), px(h), template.mode),
+                    (Some(w), None) => (px(w), scaled(orig_h, px(w) as f64 / orig_w as f64), ResizeMode::Fit),
+                    (None, Some(h)) => (scaled(orig_w, px(h) as f64 / orig_h as f64), px(h), ResizeMode::Fit),
+                    (None, None) => return Err(PixoraError::Process("Physical size needs a width or height".to_string())),
+                };
+                Ok(BoxResize { width, height, mode, ..template })
+            }
+        }
+    }
+
+    /// Resolution to record in the output, if this sizing implies one.
+    pub fn dpi(&self) -> Option<f32> {
+        match *self {
+            ResizeSizing::Physical { dpi, .. } => Some(dpi),
+            _ => None,
+        }
+    }
+}
+
+/// A target box plus the rules for getting an image into it.
+#[derive(Clone, Copy, Debug)]
+pub struct BoxResize {
+    pub width: u32,
+    pub height: u32,
+    pub mode: ResizeMode,
+    /// When false, images are never scaled above their original size.
+    /// `Exact` always stretches and ignores this.
+    pub allow_upscale: bool,
+    pub anchor: Anchor,
+    /// Padding color for `Contain`.
+    pub background: Rgba<u8>,
+    pub filter: ResampleFilter,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResizeOptions {
@@ -12,6 +144,22 @@ pub struct ResizeOptions {
     pub keep_aspect: bool,
     pub format: Option<String>,
     pub quality: Option<u8>,
+    /// Box mode for when both sides are given; overrides `keep_aspect`.
+    #[serde(default)]
+    pub mode: Option<ResizeMode>,
+    /// Defaults to true here, unlike the pipeline.
+    #[serde(default)]
+    pub allow_upscale: Option<bool>,
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// Hex padding color for `contain`; transparent when unset.
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Percentage, megapixel or physical sizing; replaces `width`/`height`.
+    #[serde(default)]
+    pub sizing: Option<ResizeSizing>,
+    #[serde(default)]
+    pub filter: ResampleFilter,
 }
 
 #[derive(Serialize)]
@@ -24,16 +172,31 @@ pub struct ResizeResult {
 }
 
 pub fn decode_data_url(data_url: &str) -> Result<(DynamicImage, String)> {
+    let (bytes, format) = data_url_bytes(data_url)?;
+    let img = decode_bytes(&bytes, &format)?;
+    Ok((img, format))
+}
+
+/// Splits a data URL into its decoded bytes and the format named in its header.
+pub fn data_url_bytes(data_url: &str) -> Result<(Vec<u8>, String)> {
     let (header, data) = data_url
         .split_once(',')
         .ok_or_else(|| PixoraError::Process("URL de datos inválida".to_string()))?;
 
-    let format = if header.contains("jpeg") || header.contains("jpg") {
+    let format = if is_raw_header(header) {
+        "raw"
+    } else if header.contains("svg") {
+        "svg"
+    } else if header.contains("jpeg") || header.contains("jpg") {
         "jpeg"
     } else if header.contains("png") {
         "png"
     } else if header.contains("webp") {
         "webp"
+    } else if header.contains("jxl") {
+        "jxl"
+    } else if header.contains("gif") {
+        "gif"
     } else {
         "jpeg"
     };
@@ -41,73 +204,205 @@ pub fn decode_data_url(data_url: &str) -> Result<(DynamicImage, String)> {
     let bytes = general_purpose::STANDARD
         .decode(data)
         .map_err(|e| PixoraError::Process(e.to_string()))?;
+    Ok((bytes, format.to_string()))
+}
 
-    let img = image::load_from_memory(&bytes).map_err(|e| PixoraError::Image(e.to_string()))?;
-    Ok((img, format.to_string()))
+/// Decodes file bytes, including formats the `image` crate can't guess on its
+/// own. `format` is the one reported by `data_url_bytes`; camera RAW files
+/// can't be told apart from plain TIFFs by content alone.
+pub fn decode_bytes(bytes: &[u8], format: &str) -> Result<DynamicImage> {
+    match format {
+        "raw" => return decode_raw(bytes),
+        "svg" => return rasterize_svg(bytes, SvgRasterOptions::default()),
+        _ => {}
+    }
+    if is_jxl(bytes) {
+        return decode_jxl(bytes);
+    }
+    image::load_from_memory(bytes).map_err(|e| PixoraError::Image(e.to_string()))
 }
 
-pub fn encode_image(img: &DynamicImage, format: &str, quality: u8) -> Result<(String, usize)> {
+/// Encodes `img` in `format`. When `dpi` is set it is written as JFIF
+/// density and EXIF resolution (JPEG), pHYs (PNG) or EXIF (WebP).
+pub fn encode_bytes(img: &DynamicImage, format: &str, quality: u8, dpi: Option<f32>) -> Result<Vec<u8>> {
     let mut buf = Cursor::new(Vec::new());
+    let exif = dpi.map(resolution_exif).transpose()?;
 
     match format {
-        "png" => img
-            .write_to(&mut buf, ImageFormat::Png)
-            .map_err(|e| PixoraError::Image(e.to_string()))?,
-        "webp" => img
-            .write_to(&mut buf, ImageFormat::WebP)
+        "png" => {
+            img.write_with_encoder(PngEncoder::new(&mut buf))
+                .map_err(|e| PixoraError::Image(e.to_string()))?;
+            if let Some(dpi) = dpi {
+                return Ok(set_png_dpi(buf.into_inner(), dpi));
+            }
+        }
+        "webp" => {
+            let mut encoder = WebPEncoder::new_lossless(&mut buf);
+            if let Some(exif) = exif {
+                encoder.set_exif_metadata(exif).map_err(|e| PixoraError::Image(e.to_string()))?;
+            }
+            img.write_with_encoder(encoder).map_err(|e| PixoraError::Image(e.to_string()))?;
+        }
+        "gif" => img
+            .write_to(&mut buf, ImageFormat::Gif)
             .map_err(|e| PixoraError::Image(e.to_string()))?,
+        "jxl" => buf.get_mut().extend(encode_jxl(img)?),
         _ => {
-            let mut encoder =
-                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality);
+            let mut encoder = JpegEncoder::new_with_quality(&mut buf, quality);
+            if let Some(dpi) = dpi {
+                encoder.set_pixel_density(PixelDensity::dpi(dpi.round().clamp(1.0, u16::MAX as f32) as u16));
+            }
+            if let Some(exif) = exif {
+                encoder.set_exif_metadata(exif).map_err(|e| PixoraError::Image(e.to_string()))?;
+            }
             encoder.encode_image(img).map_err(|e| PixoraError::Image(e.to_string()))?;
         }
     }
 
-    let bytes = buf.into_inner();
+    Ok(buf.into_inner())
+}
+
+pub fn encode_image(img: &DynamicImage, format: &str, quality: u8) -> Result<(String, usize)> {
+    encode_image_with_dpi(img, format, quality, None)
+}
+
+pub fn encode_image_with_dpi(img: &DynamicImage, format: &str, quality: u8, dpi: Option<f32>) -> Result<(String, usize)> {
+    let bytes = encode_bytes(img, format, quality, dpi)?;
     let size = bytes.len();
     let b64 = general_purpose::STANDARD.encode(&bytes);
     let mime = match format {
         "png" => "image/png",
         "webp" => "image/webp",
+        "gif" => "image/gif",
+        "jxl" => "image/jxl",
         _ => "image/jpeg",
     };
 
     Ok((format!("data:{};base64,{}", mime, b64), size))
 }
 
+pub fn parse_background(hex: Option<&str>) -> Result<Rgba<u8>> {
+    match hex {
+        Some(hex) => parse_hex_color(hex),
+        None => Ok(Rgba([0, 0, 0, 0])),
+    }
+}
+
+fn scaled(orig: u32, scale: f64) -> u32 {
+    ((orig as f64 * scale).round() as u32).max(1)
+}
+
+/// Resizes `img` into `target` according to its mode and anchor.
+pub fn resize_to_box(img: DynamicImage, target: &BoxResize) -> Result<DynamicImage> {
+    let (orig_w, orig_h) = img.dimensions();
+    let (box_w, box_h) = (target.width.max(1), target.height.max(1));
+    let sx = box_w as f64 / orig_w as f64;
+    let sy = box_h as f64 / orig_h as f64;
+    let limit = |scale: f64| if target.allow_upscale { scale } else { scale.min(1.0) };
+    let resize = |img: DynamicImage, scale: f64| {
+        if (scale - 1.0).abs() < 1e-4 {
+            Ok(img)
+        } else {
+            resample(&img, scaled(orig_w, scale), scaled(orig_h, scale), target.filter)
+        }
+    };
+
+    Ok(match target.mode {
+        ResizeMode::Exact => {
+            if (box_w, box_h) == (orig_w, orig_h) {
+                img
+            } else {
+                resample(&img, box_w, box_h, target.filter)?
+            }
+        }
+        ResizeMode::Fit => resize(img, limit(sx.min(sy)))?,
+        ResizeMode::Cover => {
+            let img = resize(img, limit(sx.max(sy)))?;
+            let (w, h) = img.dimensions();
+            let (cw, ch) = (w.min(box_w), h.min(box_h));
+            if (cw, ch) == (w, h) {
+                return Ok(img);
+            }
+            let (x, y) = target.anchor.offset(w - cw, h - ch);
+            img.crop_imm(x, y, cw, ch)
+        }
+        ResizeMode::Contain => {
+            let img = resize(img, limit(sx.min(sy)))?;
+            let (w, h) = img.dimensions();
+            if (w, h) == (box_w, box_h) {
+                return Ok(img);
+            }
+            let mut canvas = RgbaImage::from_pixel(box_w, box_h, target.background);
+            let (x, y) = target.anchor.offset(box_w.saturating_sub(w), box_h.saturating_sub(h));
+            imageops::overlay(&mut canvas, &img.to_rgba8(), x as i64, y as i64);
+            DynamicImage::ImageRgba8(canvas)
+        }
+    })
+}
+
+/// Turns the command options into a box resize. With only one side given,
+/// the other follows the aspect ratio; the legacy `keep_aspect` flag maps
+/// to `fit` or `exact` when no mode is set.
+fn box_for(orig_w: u32, orig_h: u32, options: &ResizeOptions) -> Result<BoxResize> {
+    let allow_upscale = options.allow_upscale.unwrap_or(true);
+    let (width, height, mode) = match (options.width, options.height) {
+        (Some(w), Some(h)) => {
+            let legacy = if options.keep_aspect { ResizeMode::Fit } else { ResizeMode::Exact };
+            (w, h, options.mode.unwrap_or(legacy))
+        }
+        (Some(w), None) => (w, scaled(orig_h, w as f64 / orig_w as f64), ResizeMode::Fit),
+        (None, Some(h)) => (scaled(orig_w, h as f64 / orig_h as f64), h, ResizeMode::Fit),
+        (None, None) => (orig_w, orig_h, ResizeMode::Fit),
+    };
+
+    let target = BoxResize {
+        width,
+        height,
+        mode,
+        allow_upscale,
+        anchor: options.anchor,
+        background: parse_background(options.background.as_deref())?,
+        filter: options.filter,
+    };
+    match &options.sizing {
+        Some(sizing) => sizing.target(orig_w, orig_h, target),
+        None => Ok(target),
+    }
+}
+
 #[tauri::command]
 pub async fn resize_image(data_url: String, options: ResizeOptions) -> Result<ResizeResult> {
     tauri::async_runtime::spawn_blocking(move || {
-        let (img, orig_format) = decode_data_url(&data_url)?;
-        let (orig_w, orig_h) = img.dimensions();
+        let (bytes, orig_format) = data_url_bytes(&data_url)?;
         let format = options.format.as_deref().unwrap_or(&orig_format).to_string();
+        check_jxl_quality(&format, options.quality)?;
         let quality = options.quality.unwrap_or(85).clamp(1, 100);
 
-        let (new_w, new_h) = match (options.width, options.height) {
-            (Some(w), Some(h)) => {
-                if options.keep_aspect {
-                    let ratio = (w as f64 / orig_w as f64).min(h as f64 / orig_h as f64);
-                    ((orig_w as f64 * ratio) as u32, (orig_h as f64 * ratio) as u32)
-                } else {
-                    (w, h)
-                }
-            }
-            (Some(w), None) => {
-                let ratio = w as f64 / orig_w as f64;
-                (w, (orig_h as f64 * ratio) as u32)
-            }
-            (None, Some(h)) => {
-                let ratio = h as f64 / orig_h as f64;
-                ((orig_w as f64 * ratio) as u32, h)
-            }
-            (None, None) => (orig_w, orig_h),
-        };
+        if let Some(animation) = decode_frames(&bytes)? {
+            let (orig_w, orig_h) = animation.frames[0].buffer().dimensions();
+            let target = box_for(orig_w, orig_h, &options)?;
+            let frames = map_frames(animation.frames, |img| resize_to_box(img, &target))?;
+            let (new_w, new_h) = frames[0].buffer().dimensions();
+            let encoded = encode_animation(&frames, &format, quality, animation.loop_count)?;
+            let size_bytes = encoded.len();
+            let b64 = general_purpose::STANDARD.encode(&encoded);
 
-        let new_w = new_w.max(1);
-        let new_h = new_h.max(1);
+            return Ok(ResizeResult {
+                data_url: format!("data:image/{};base64,{}", format, b64),
+                width: new_w,
+                height: new_h,
+                size_bytes,
+            });
+        }
 
-        let resized = img.resize_exact(new_w, new_h, FilterType::Lanczos3);
-        let (data_url_out, size_bytes) = encode_image(&resized, &format, quality)?;
+        let img = decode_bytes(&bytes, &orig_format)?;
+        let (orig_w, orig_h) = img.dimensions();
+        let target = box_for(orig_w, orig_h, &options)?;
+
+        let resized = resize_to_box(img, &target)?;
+        let (new_w, new_h) = resized.dimensions();
+        let dpi = options.sizing.and_then(|s| s.dpi());
+        let (data_url_out, size_bytes) = encode_image_with_dpi(&resized, &format, quality, dpi)?;
 
         Ok(ResizeResult {
             data_url: data_url_out,
@@ -117,4 +412,3 @@ pub async fn resize_image(data_url: String, options: ResizeOptions) -> Result<Re
         })
     }).await.map_err(|e| PixoraError::Process(e.to_string()))?
 }
-
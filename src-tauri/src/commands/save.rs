@@ -4,31 +4,56 @@ use std::path::PathBuf;
 use tauri::{AppHandle, Emitter};
 use crate::error::{PixoraError, Result};
 
+use super::raw::raw_mime;
+use super::resize::encode_image;
+use super::svg::{rasterize_svg, SvgRasterOptions};
+
+/// Loads an image file as a data URL. SVGs are rasterized to PNG with `svg`
+/// (intrinsic size when omitted); every other format is passed through as-is.
 #[tauri::command]
-pub async fn load_image_file(path: String) -> Result<String> {
+pub async fn load_image_file(path: String, svg: Option<SvgRasterOptions>) -> Result<String> {
     let path_buf = PathBuf::from(&path);
     let ext = path_buf
         .extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_lowercase());
 
-    let allowed = ["jpg", "jpeg", "png", "webp", "gif", "tiff", "tif", "bmp"];
+    let allowed = ["jpg", "jpeg", "png", "webp", "gif", "tiff", "tif", "bmp", "jxl", "svg"];
     let ext_str = ext.as_deref().unwrap_or("");
-    if !allowed.contains(&ext_str) {
+    if !allowed.contains(&ext_str) && raw_mime(ext_str).is_none() {
         return Err(PixoraError::Process(format!("Formato no permitido: {:?}", ext)));
     }
 
     let bytes = tokio::fs::read(&path_buf).await?;
+    if ext_str == "svg" {
+        let options = svg.unwrap_or_default();
+        return tauri::async_runtime::spawn_blocking(move || svg_data_url(&bytes, options))
+            .await
+            .map_err(|e| PixoraError::Process(e.to_string()))?;
+    }
     let b64 = general_purpose::STANDARD.encode(&bytes);
-    let mime = match ext_str {
+    let mime = mime_for_extension(ext_str);
+    Ok(format!("data:{};base64,{}", mime, b64))
+}
+
+fn svg_data_url(bytes: &[u8], options: SvgRasterOptions) -> Result<String> {
+    let img = rasterize_svg(bytes, options)?;
+    let (data_url, _) = encode_image(&img, "png", 100)?;
+    Ok(data_url)
+}
+
+/// MIME type for an imported file. Camera RAW files keep a RAW type so the
+/// decoder knows to develop them instead of reading their embedded TIFF preview.
+fn mime_for_extension(ext: &str) -> &'static str {
+    match ext {
         "png" => "image/png",
         "webp" => "image/webp",
         "gif" => "image/gif",
         "tiff" | "tif" => "image/tiff",
         "bmp" => "image/bmp",
-        _ => "image/jpeg",
-    };
-    Ok(format!("data:{};base64,{}", mime, b64))
+        "jxl" => "image/jxl",
+        other => raw_mime(other).unwrap_or("image/jpeg"),
+    }
 }
 
 #[tauri::command]
@@ -62,6 +87,7 @@ pub struct ZipEntry {
 pub async fn import_images_batch(
     app_handle: AppHandle,
     paths: Vec<String>,
+    svg: Option<SvgRasterOptions>,
 ) -> Result<()> {
     let total = paths.len();
 
@@ -76,17 +102,20 @@ pub async fn import_images_batch(
         let _ = app_handle.emit("import-progress", progress);
 
         if let Ok(bytes) = std::fs::read(&path_buf) {
-            let ext = path_buf.extension().and_then(|e| e.to_str()).unwrap_or("jpg");
-            let b64 = general_purpose::STANDARD.encode(&bytes);
-            let mime = match ext.to_lowercase().as_str() {
-                "png" => "image/png",
-                "webp" => "image/webp",
-                _ => "image/jpeg",
+            let ext = path_buf.extension().and_then(|e| e.to_str()).unwrap_or("jpg").to_lowercase();
+            let data_url = if ext == "svg" {
+                match svg_data_url(&bytes, svg.unwrap_or_default()) {
+                    Ok(data_url) => data_url,
+                    Err(_) => continue,
+                }
+            } else {
+                let b64 = general_purpose::STANDARD.encode(&bytes);
+                format!("data:{};base64,{}", mime_for_extension(&ext), b64)
             };
-            
+
             let _ = app_handle.emit("import-new-image", ZipEntry {
                 path,
-                name: format!("data:{};base64,{}", mime, b64),
+                name: data_url,
             });
         }
     }
@@ -0,0 +1,79 @@
+use image::DynamicImage;
+use imageproc::filter::gaussian_blur_f32;
+use serde::Deserialize;
+
+/// Unsharp mask settings.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SharpenSettings {
+    /// Strength in percent; 100 adds the full difference to the blurred copy.
+    #[serde(default = "default_amount")]
+    pub amount: f32,
+    /// Gaussian blur sigma in pixels.
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Differences at or below this (0–255) are left alone, so flat areas
+    /// and noise are not sharpened.
+    #[serde(default = "default_threshold")]
+    pub threshold: u8,
+    /// Derive the amount from the downscale ratio instead of `amount`.
+    #[serde(default)]
+    pub auto: bool,
+}
+
+fn default_amount() -> f32 {
+    50.0
+}
+
+fn default_radius() -> f32 {
+    0.8
+}
+
+fn default_threshold() -> u8 {
+    2
+}
+
+/// Percent of sharpening added per halving of the image size in `auto` mode.
+const AUTO_PER_HALVING: f32 = 40.0;
+const AUTO_MAX: f32 = 150.0;
+
+impl SharpenSettings {
+    /// Amount to use after an image was shrunk by `downscale` (original over
+    /// new size, 1.0 when not resized). Auto strength is zero without a downscale.
+    pub fn effective_amount(&self, downscale: f32) -> f32 {
+        if self.auto {
+            (downscale.max(1.0).log2() * AUTO_PER_HALVING).min(AUTO_MAX)
+        } else {
+            self.amount.max(0.0)
+        }
+    }
+}
+
+/// Unsharp mask on the color channels; alpha is left untouched.
+pub fn apply_sharpen(img: DynamicImage, settings: &SharpenSettings, downscale: f32) -> DynamicImage {
+    let amount = settings.effective_amount(downscale) / 100.0;
+    if amount <= 0.0 || settings.radius <= 0.0 {
+        return img;
+    }
+
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba8();
+    let blurred = gaussian_blur_f32(&rgba, settings.radius);
+    let threshold = settings.threshold as f32;
+
+    for (px, soft) in rgba.pixels_mut().zip(blurred.pixels()) {
+        for c in 0..3 {
+            let orig = px[c] as f32;
+            let diff = orig - soft[c] as f32;
+            if diff.abs() > threshold {
+                px[c] = (orig + diff * amount).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+}
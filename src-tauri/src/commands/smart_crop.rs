@@ -0,0 +1,253 @@
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView};
+use imageproc::gradients::sobel_gradients;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{PixoraError, Result};
+use super::remove_bg::{foreground_mask, model_available};
+use super::jxl::check_jxl_quality;
+use super::resize::{decode_data_url, encode_image};
+use super::transform::{aspect_rect, parse_ratio, Anchor};
+
+/// Longest side of the map the crop window is searched on.
+const WORK_PX: u32 = 256;
+/// Neighbourhood radius for local entropy, in work-map pixels.
+const ENTROPY_RADIUS: i64 = 4;
+/// How much a window loses for sitting at the very edge rather than the
+/// center; breaks ties on flat images in favour of a centered crop.
+const CENTER_BIAS: f64 = 0.1;
+/// Share of the best window's saliency a window re-centered on the focal
+/// point must keep to be preferred.
+const RECENTER_KEEP: f64 = 0.95;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Saliency {
+    /// The subject mask when the background-removal model is already
+    /// downloaded, local entropy otherwise.
+    #[default]
+    Auto,
+    Entropy,
+    Edges,
+    /// Foreground mask from the background-removal model; downloads it if needed.
+    Subject,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCropSettings {
+    /// "W:H", e.g. "1:1" or "9:16".
+    pub ratio: String,
+    #[serde(default)]
+    pub saliency: Saliency,
+}
+
+/// Crop window chosen by the smart crop, in pixels of the image it was
+/// measured on.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCrop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Center of interest as fractions (0–1) of the source size.
+    pub focal_x: f32,
+    pub focal_y: f32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartCropResult {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+    pub crop: SmartCrop,
+}
+
+/// Interest map of `img` at `width`×`height`, one weight per pixel.
+fn saliency_map(app: &AppHandle, img: &DynamicImage, mode: Saliency, width: u32, height: u32) -> Result<Vec<f64>> {
+    if mode == Saliency::Subject {
+        let mask = foreground_mask(app, img, width, height)?;
+        return Ok(mask.pixels().map(|p| p[0] as f64 / 255.0).collect());
+    }
+
+    let gray = imageops::resize(&img.to_luma8(), width, height, FilterType::Triangle);
+    if mode == Saliency::Edges {
+        return Ok(sobel_gradients(&gray).pixels().map(|p| p[0] as f64).collect());
+    }
+
+    // Shannon entropy of 16-level luma in a small neighbourhood: high on
+    // texture and detail, zero on flat backdrops.
+    let levels: Vec<usize> = gray.pixels().map(|p| (p[0] >> 4) as usize).collect();
+    let (w, h) = (width as i64, height as i64);
+    let mut map = Vec::with_capacity(levels.len());
+    for y in 0..h {
+        for x in 0..w {
+            let mut hist = [0u32; 16];
+            let mut count = 0u32;
+            for ny in (y - ENTROPY_RADIUS).max(0)..=(y + ENTROPY_RADIUS).min(h - 1) {
+                for nx in (x - ENTROPY_RADIUS).max(0)..=(x + ENTROPY_RADIUS).min(w - 1) {
+                    hist[levels[(ny * w + nx) as usize]] += 1;
+                    count += 1;
+                }
+            }
+            let entropy: f64 = hist
+                .iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| {
+                    let p = n as f64 / count as f64;
+                    -p * p.log2()
+                })
+                .sum();
+            map.push(entropy);
+        }
+    }
+    Ok(map)
+}
+
+/// Weighted centroid of `map` inside the window, or its center when the
+/// window holds no weight.
+fn centroid(map: &[f64], map_w: u32, x: u32, y: u32, w: u32, h: u32) -> (f64, f64) {
+    let (mut sum, mut sx, mut sy) = (0.0, 0.0, 0.0);
+    for py in y..y + h {
+        for px in x..x + w {
+            let v = map[(py * map_w + px) as usize];
+            sum += v;
+            sx += v * (px as f64 + 0.5);
+            sy += v * (py as f64 + 0.5);
+        }
+    }
+    if sum <= f64::EPSILON {
+        return (x as f64 + w as f64 / 2.0, y as f64 + h as f64 / 2.0);
+    }
+    (sx / sum, sy / sum)
+}
+
+/// Position of a `win_w`×`win_h` window centered on (`fx`, `fy`), kept inside the map.
+fn center_on(fx: f64, fy: f64, win_w: u32, win_h: u32, map_w: u32, map_h: u32) -> (u32, u32) {
+    let x = (fx - win_w as f64 / 2.0).round().clamp(0.0, (map_w - win_w) as f64) as u32;
+    let y = (fy - win_h as f64 / 2.0).round().clamp(0.0, (map_h - win_h) as f64) as u32;
+    (x, y)
+}
+
+/// Picks the `settings.ratio` window that keeps the most interesting part
+/// of `img`. With a subject mask the window is centered on the subject;
+/// otherwise it is the window holding the most saliency, nudged to center
+/// its focal point when that loses little.
+pub fn find_smart_crop(app: &AppHandle, img: &DynamicImage, settings: &SmartCropSettings) -> Result<SmartCrop> {
+    let (width, height) = img.dimensions();
+    let (rw, rh) = parse_ratio(&settings.ratio)?;
+    let (_, _, cw, ch) = aspect_rect(width, height, rw, rh, Anchor::Center);
+
+    let mode = match settings.saliency {
+        Saliency::Auto if model_available() => Saliency::Subject,
+        Saliency::Auto => Saliency::Entropy,
+        mode => mode,
+    };
+
+    let scale = WORK_PX as f64 / width.max(height) as f64;
+    let scale = scale.min(1.0);
+    let map_w = ((width as f64 * scale).round() as u32).max(1);
+    let map_h = ((height as f64 * scale).round() as u32).max(1);
+    let sx = map_w as f64 / width as f64;
+    let sy = map_h as f64 / height as f64;
+    let win_w = ((cw as f64 * sx).round() as u32).clamp(1, map_w);
+    let win_h = ((ch as f64 * sy).round() as u32).clamp(1, map_h);
+
+    let map = saliency_map(app, img, mode, map_w, map_h)?;
+
+    let (wx, wy) = if mode == Saliency::Subject {
+        let (fx, fy) = centroid(&map, map_w, 0, 0, map_w, map_h);
+        center_on(fx, fy, win_w, win_h, map_w, map_h)
+    } else {
+        // Summed-area table so every window position costs four lookups.
+        let stride = map_w as usize + 1;
+        let mut table = vec![0.0f64; stride * (map_h as usize + 1)];
+        for y in 0..map_h as usize {
+            let mut row = 0.0;
+            for x in 0..map_w as usize {
+                row += map[y * map_w as usize + x];
+                table[(y + 1) * stride + x + 1] = table[y * stride + x + 1] + row;
+            }
+        }
+        let window_sum = |x: usize, y: usize| {
+            let (x2, y2) = (x + win_w as usize, y + win_h as usize);
+            table[y2 * stride + x2] - table[y * stride + x2] - table[y2 * stride + x] + table[y * stride + x]
+        };
+
+        let half_diag = ((map_w as f64).hypot(map_h as f64) / 2.0).max(1.0);
+        let mut best = (0u32, 0u32);
+        let mut best_score = f64::MIN;
+        for y in 0..=(map_h - win_h) {
+            for x in 0..=(map_w - win_w) {
+                let dx = x as f64 + win_w as f64 / 2.0 - map_w as f64 / 2.0;
+                let dy = y as f64 + win_h as f64 / 2.0 - map_h as f64 / 2.0;
+                let bias = 1.0 - CENTER_BIAS * dx.hypot(dy) / half_diag;
+                let score = (window_sum(x as usize, y as usize) + f64::EPSILON) * bias;
+                if score > best_score {
+                    best_score = score;
+                    best = (x, y);
+                }
+            }
+        }
+
+        // Several windows often hold the whole subject; prefer the one that
+        // puts it in the middle.
+        let (fx, fy) = centroid(&map, map_w, best.0, best.1, win_w, win_h);
+        let centered = center_on(fx, fy, win_w, win_h, map_w, map_h);
+        let best_sum = window_sum(best.0 as usize, best.1 as usize);
+        if window_sum(centered.0 as usize, centered.1 as usize) >= best_sum * RECENTER_KEEP {
+            centered
+        } else {
+            best
+        }
+    };
+
+    let (fx, fy) = centroid(&map, map_w, wx, wy, win_w, win_h);
+    let x = ((wx as f64 / sx).round() as u32).min(width - cw);
+    let y = ((wy as f64 / sy).round() as u32).min(height - ch);
+    Ok(SmartCrop {
+        x,
+        y,
+        width: cw,
+        height: ch,
+        focal_x: (fx / map_w as f64) as f32,
+        focal_y: (fy / map_h as f64) as f32,
+    })
+}
+
+pub fn apply_smart_crop(img: DynamicImage, crop: SmartCrop) -> DynamicImage {
+    if (crop.x, crop.y, crop.width, crop.height) == (0, 0, img.width(), img.height()) {
+        return img;
+    }
+    img.crop_imm(crop.x, crop.y, crop.width, crop.height)
+}
+
+#[tauri::command]
+pub async fn smart_crop_image(
+    app: AppHandle,
+    data_url: String,
+    smart_crop: SmartCropSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<SmartCropResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (img, orig_format) = decode_data_url(&data_url)?;
+        let format = format.unwrap_or(orig_format);
+        check_jxl_quality(&format, quality)?;
+        let quality = quality.unwrap_or(85).clamp(1, 100);
+
+        let crop = find_smart_crop(&app, &img, &smart_crop)?;
+        let out = apply_smart_crop(img, crop);
+        let (data_url, size_bytes) = encode_image(&out, &format, quality)?;
+        Ok(SmartCropResult {
+            data_url,
+            width: out.width(),
+            height: out.height(),
+            size_bytes,
+            crop,
+        })
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
@@ -0,0 +1,89 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, ImageHrefResolver, Options, Tree};
+use serde::Deserialize;
+use std::sync::{Arc, OnceLock};
+
+use crate::error::{PixoraError, Result};
+
+/// Largest raster side we are willing to allocate for an SVG.
+const MAX_SIDE: u32 = 16384;
+/// CSS reference resolution; SVG user units are pixels at this DPI.
+const CSS_DPI: f32 = 96.0;
+
+static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+/// Target size for rasterizing an SVG. With only one side set the other
+/// follows the aspect ratio; with both set the drawing is fitted and centered
+/// in that box. Without either, the intrinsic size is scaled by `dpi / 96`.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SvgRasterOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub dpi: Option<f32>,
+}
+
+fn system_fonts() -> Arc<fontdb::Database> {
+    FONTS
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// Rasterizes SVG bytes to RGBA. Only images embedded as data URLs are
+/// rendered; external references (files or URLs) are ignored.
+pub fn rasterize_svg(bytes: &[u8], options: SvgRasterOptions) -> Result<DynamicImage> {
+    let dpi = options.dpi.filter(|d| *d > 0.0).unwrap_or(CSS_DPI);
+    let opts = Options {
+        dpi,
+        image_href_resolver: ImageHrefResolver {
+            resolve_data: ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        fontdb: system_fonts(),
+        ..Default::default()
+    };
+    let tree = Tree::from_data(bytes, &opts).map_err(|e| PixoraError::Image(format!("SVG parse failed: {}", e)))?;
+
+    let size = tree.size();
+    let (sw, sh) = (size.width(), size.height());
+    let (scale, width, height) = match (options.width, options.height) {
+        (Some(w), Some(h)) => ((w as f32 / sw).min(h as f32 / sh), w, h),
+        (Some(w), None) => {
+            let scale = w as f32 / sw;
+            (scale, w, (sh * scale).round() as u32)
+        }
+        (None, Some(h)) => {
+            let scale = h as f32 / sh;
+            (scale, (sw * scale).round() as u32, h)
+        }
+        (None, None) => {
+            let scale = dpi / CSS_DPI;
+            (scale, (sw * scale).round() as u32, (sh * scale).round() as u32)
+        }
+    };
+    let (width, height) = (width.max(1), height.max(1));
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Err(PixoraError::Process(format!(
+            "SVG raster size {}x{} exceeds the {} px limit",
+            width, height, MAX_SIDE
+        )));
+    }
+
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| PixoraError::Process("Could not allocate SVG canvas".to_string()))?;
+    let dx = (width as f32 - sw * scale) / 2.0;
+    let dy = (height as f32 - sh * scale) / 2.0;
+    resvg::render(&tree, Transform::from_scale(scale, scale).post_translate(dx, dy), &mut pixmap.as_mut());
+
+    let mut out = RgbaImage::new(width, height);
+    for (dst, src) in out.pixels_mut().zip(pixmap.pixels()) {
+        let c = src.demultiply();
+        *dst = Rgba([c.red(), c.green(), c.blue(), c.alpha()]);
+    }
+    Ok(DynamicImage::ImageRgba8(out))
+}
@@ -0,0 +1,110 @@
+use ab_glyph::{point, Font, FontArc, GlyphId, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use std::sync::OnceLock;
+
+use crate::error::{PixoraError, Result};
+
+/// DejaVu Sans, shipped with the app so text renders the same everywhere.
+/// License in `fonts/LICENSE-DejaVu.txt`.
+static BUNDLED_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+static BUNDLED: OnceLock<FontArc> = OnceLock::new();
+
+/// Loads a TTF/OTF file, or the bundled font when `path` is `None`.
+pub fn load_font(path: Option<&str>) -> Result<FontArc> {
+    match path {
+        Some(path) => FontArc::try_from_vec(std::fs::read(path)?)
+            .map_err(|_| PixoraError::Process(format!("Not a usable TTF/OTF font: {}", path))),
+        None => Ok(BUNDLED
+            .get_or_init(|| FontArc::try_from_slice(BUNDLED_FONT).expect("bundled font is valid"))
+            .clone()),
+    }
+}
+
+/// Advance width of `text` on a single line at `px`.
+pub fn line_width(font: &FontArc, px: f32, text: &str) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(px));
+    let mut width = 0.0;
+    let mut prev: Option<GlyphId> = None;
+    for ch in text.chars() {
+        let id = font.glyph_id(ch);
+        if let Some(prev) = prev {
+            width += scaled.kern(prev, id);
+        }
+        width += scaled.h_advance(id);
+        prev = Some(id);
+    }
+    width
+}
+
+/// Line height (ascent to descent) at `px`.
+pub fn line_height(font: &FontArc, px: f32) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(px));
+    scaled.ascent() - scaled.descent()
+}
+
+/// Draws one line of text with its top-left at (`x`, `y`), combining glyph
+/// coverage into the alpha channel so the result composites cleanly.
+pub fn draw_line(canvas: &mut RgbaImage, font: &FontArc, px: f32, text: &str, x: f32, y: f32, color: Rgba<u8>) {
+    let scale = PxScale::from(px);
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    let mut prev: Option<GlyphId> = None;
+
+    for ch in text.chars() {
+        let id = font.glyph_id(ch);
+        if let Some(prev) = prev {
+            caret += scaled.kern(prev, id);
+        }
+        let glyph = id.with_scale_and_position(scale, point(caret, y + scaled.ascent()));
+        caret += scaled.h_advance(id);
+        prev = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else { continue };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px < 0 || py < 0 || px >= canvas.width() as i64 || py >= canvas.height() as i64 {
+                return;
+            }
+            let alpha = (coverage.clamp(0.0, 1.0) * color[3] as f32).round() as u8;
+            let dst = canvas.get_pixel_mut(px as u32, py as u32);
+            if alpha > dst[3] {
+                *dst = Rgba([color[0], color[1], color[2], alpha]);
+            }
+        });
+    }
+}
+
+/// Renders one line of text onto a tightly sized transparent image.
+pub fn render_line(font: &FontArc, px: f32, text: &str, color: Rgba<u8>) -> RgbaImage {
+    let width = line_width(font, px, text).ceil().max(1.0) as u32;
+    let height = line_height(font, px).ceil().max(1.0) as u32;
+    let mut canvas = RgbaImage::new(width, height);
+    draw_line(&mut canvas, font, px, text, 0.0, 0.0, color);
+    canvas
+}
+
+/// Splits `text` into lines no wider than `max_width` at `px`, breaking at
+/// spaces and keeping explicit line breaks. A word wider than the limit
+/// gets a line of its own.
+pub fn wrap_text(font: &FontArc, px: f32, text: &str, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if line.is_empty() {
+                line.push_str(word);
+                continue;
+            }
+            let candidate = format!("{} {}", line, word);
+            if line_width(font, px, &candidate) <= max_width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
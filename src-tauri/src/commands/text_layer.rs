@@ -0,0 +1,278 @@
+use image::{imageops, DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use imageproc::distance_transform::euclidean_squared_distance_transform;
+use imageproc::filter::gaussian_blur_f32;
+use serde::Deserialize;
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::text::{draw_line, line_height, line_width, load_font, wrap_text};
+use super::transform::Anchor;
+
+/// Text that doesn't fit its area is shrunk in steps of this factor...
+const SHRINK: f32 = 0.9;
+/// ...down to this size in pixels.
+const MIN_PX: f32 = 6.0;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// Rectangle as fractions (0.0–1.0) of the image size.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextArea {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        TextArea { x: 0.0, y: 0.0, width: 1.0, height: 1.0 }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStroke {
+    /// Hex color, black by default.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Outline thickness in percent of the font size.
+    #[serde(default = "default_stroke_width")]
+    pub width: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextShadow {
+    /// Hex color, translucent black by default.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Offsets and blur radius in percent of the font size.
+    #[serde(default = "default_shadow_offset")]
+    pub offset_x: f32,
+    #[serde(default = "default_shadow_offset")]
+    pub offset_y: f32,
+    #[serde(default = "default_shadow_blur")]
+    pub blur: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextBackground {
+    pub color: String,
+    /// Space around the text in percent of the font size.
+    #[serde(default = "default_padding")]
+    pub padding: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextLayer {
+    /// May span several lines; long lines wrap to the area width.
+    pub text: String,
+    /// TTF/OTF file; the bundled DejaVu Sans when unset.
+    #[serde(default)]
+    pub font_path: Option<String>,
+    /// Font size in percent of the image width. Shrunk if the text would
+    /// not fit in `area`.
+    #[serde(default = "default_size")]
+    pub size: f32,
+    /// Hex color, white by default.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub align: TextAlign,
+    /// Distance between baselines as a multiple of the line height.
+    #[serde(default = "default_line_spacing")]
+    pub line_spacing: f32,
+    /// Box the text wraps and is placed in; the whole image by default.
+    #[serde(default)]
+    pub area: TextArea,
+    /// Where the text block sits inside `area`.
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub stroke: Option<TextStroke>,
+    #[serde(default)]
+    pub shadow: Option<TextShadow>,
+    /// Filled box behind the text block.
+    #[serde(default)]
+    pub background: Option<TextBackground>,
+}
+
+fn default_size() -> f32 {
+    6.0
+}
+
+fn default_line_spacing() -> f32 {
+    1.2
+}
+
+fn default_stroke_width() -> f32 {
+    8.0
+}
+
+fn default_shadow_offset() -> f32 {
+    4.0
+}
+
+fn default_shadow_blur() -> f32 {
+    6.0
+}
+
+fn default_padding() -> f32 {
+    40.0
+}
+
+/// Composites `color`, with `mask` as coverage, over `layer` at (`dx`, `dy`).
+fn paint(layer: &mut RgbaImage, mask: &GrayImage, color: Rgba<u8>, dx: i64, dy: i64) {
+    for (x, y, m) in mask.enumerate_pixels() {
+        let (lx, ly) = (x as i64 + dx, y as i64 + dy);
+        if m[0] == 0 || lx < 0 || ly < 0 || lx >= layer.width() as i64 || ly >= layer.height() as i64 {
+            continue;
+        }
+        let src_a = m[0] as f32 / 255.0 * color[3] as f32 / 255.0;
+        let dst = layer.get_pixel_mut(lx as u32, ly as u32);
+        let dst_a = dst[3] as f32 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a <= 0.0 {
+            continue;
+        }
+        for c in 0..3 {
+            let v = (color[c] as f32 * src_a + dst[c] as f32 * dst_a * (1.0 - src_a)) / out_a;
+            dst[c] = v.round().clamp(0.0, 255.0) as u8;
+        }
+        dst[3] = (out_a * 255.0).round() as u8;
+    }
+}
+
+/// Grows glyph coverage outwards by `radius` pixels, keeping the edge
+/// anti-aliased.
+fn outline(mask: &GrayImage, radius: f32) -> GrayImage {
+    let solid = GrayImage::from_fn(mask.width(), mask.height(), |x, y| Luma([u8::from(mask.get_pixel(x, y)[0] >= 128)]));
+    let distance = euclidean_squared_distance_transform(&solid);
+    GrayImage::from_fn(mask.width(), mask.height(), |x, y| {
+        let grown = (radius + 0.5 - distance.get_pixel(x, y)[0].sqrt() as f32).clamp(0.0, 1.0);
+        Luma([mask.get_pixel(x, y)[0].max((grown * 255.0).round() as u8)])
+    })
+}
+
+fn draw_layer(canvas: &mut RgbaImage, layer: &TextLayer) -> Result<()> {
+    if layer.text.trim().is_empty() {
+        return Ok(());
+    }
+    let font = load_font(layer.font_path.as_deref())?;
+    let color = match layer.color.as_deref() {
+        Some(hex) => parse_hex_color(hex)?,
+        None => Rgba([255, 255, 255, 255]),
+    };
+    let (width, height) = canvas.dimensions();
+    let area = layer.area;
+    let ax = (area.x.clamp(0.0, 1.0) * width as f32).round();
+    let ay = (area.y.clamp(0.0, 1.0) * height as f32).round();
+    let aw = (area.width.clamp(0.0, 1.0) * width as f32).round().min(width as f32 - ax);
+    let ah = (area.height.clamp(0.0, 1.0) * height as f32).round().min(height as f32 - ay);
+    if aw < 1.0 || ah < 1.0 {
+        return Err(PixoraError::Process("Text area is outside the image".to_string()));
+    }
+
+    // Shrink until the wrapped block, with its background padding, fits.
+    let spacing = layer.line_spacing.max(0.5);
+    let padding_share = layer.background.as_ref().map_or(0.0, |b| b.padding.max(0.0) / 100.0);
+    let mut px = (width as f32 * layer.size.max(0.0) / 100.0).max(MIN_PX);
+    let (lines, widths, block_w, block_h) = loop {
+        let pad = px * padding_share;
+        let lines = wrap_text(&font, px, &layer.text, aw - 2.0 * pad);
+        let widths: Vec<f32> = lines.iter().map(|l| line_width(&font, px, l)).collect();
+        let block_w = widths.iter().cloned().fold(0.0, f32::max);
+        let block_h = line_height(&font, px) * (1.0 + spacing * (lines.len() - 1) as f32);
+        let fits = block_w + 2.0 * pad <= aw && block_h + 2.0 * pad <= ah;
+        if fits || px * SHRINK < MIN_PX {
+            break (lines, widths, block_w, block_h);
+        }
+        px *= SHRINK;
+    };
+
+    let pad = (px * padding_share).round();
+    let box_w = (block_w + 2.0 * pad).ceil() as u32;
+    let box_h = (block_h + 2.0 * pad).ceil() as u32;
+    let (ox, oy) = layer.anchor.offset((aw as u32).saturating_sub(box_w), (ah as u32).saturating_sub(box_h));
+
+    // Room around the box for the stroke and shadow to spill into.
+    let stroke_px = layer.stroke.as_ref().map_or(0.0, |s| px * s.width.max(0.0) / 100.0);
+    let shadow = layer.shadow.as_ref().map(|s| {
+        (px * s.offset_x / 100.0, px * s.offset_y / 100.0, px * s.blur.max(0.0) / 100.0)
+    });
+    let spill = shadow.map_or(0.0, |(sx, sy, blur)| sx.abs().max(sy.abs()) + 3.0 * blur);
+    let margin = (stroke_px + spill).ceil() as u32 + 1;
+
+    let (lw, lh) = (box_w + 2 * margin, box_h + 2 * margin);
+    let mut glyphs = RgbaImage::new(lw, lh);
+    let advance = line_height(&font, px) * spacing;
+    for (i, (line, line_w)) in lines.iter().zip(&widths).enumerate() {
+        let shift = match layer.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (block_w - line_w) / 2.0,
+            TextAlign::Right => block_w - line_w,
+        };
+        let x = margin as f32 + pad + shift;
+        let y = margin as f32 + pad + i as f32 * advance;
+        draw_line(&mut glyphs, &font, px, line, x, y, Rgba([255, 255, 255, 255]));
+    }
+    let fill_mask = GrayImage::from_fn(lw, lh, |x, y| Luma([glyphs.get_pixel(x, y)[3]]));
+    let stroke_mask = (stroke_px > 0.0).then(|| outline(&fill_mask, stroke_px));
+
+    let mut composed = RgbaImage::new(lw, lh);
+    if let Some(background) = &layer.background {
+        let fill = parse_hex_color(&background.color)?;
+        let rect = GrayImage::from_pixel(box_w, box_h, Luma([255]));
+        paint(&mut composed, &rect, fill, margin as i64, margin as i64);
+    }
+    if let (Some((sx, sy, blur)), Some(settings)) = (shadow, &layer.shadow) {
+        let shadow_color = match settings.color.as_deref() {
+            Some(hex) => parse_hex_color(hex)?,
+            None => Rgba([0, 0, 0, 160]),
+        };
+        let base = stroke_mask.as_ref().unwrap_or(&fill_mask);
+        let mask = if blur > 0.0 { gaussian_blur_f32(base, blur) } else { base.clone() };
+        paint(&mut composed, &mask, shadow_color, sx.round() as i64, sy.round() as i64);
+    }
+    if let (Some(mask), Some(settings)) = (&stroke_mask, &layer.stroke) {
+        let stroke_color = match settings.color.as_deref() {
+            Some(hex) => parse_hex_color(hex)?,
+            None => Rgba([0, 0, 0, 255]),
+        };
+        paint(&mut composed, mask, stroke_color, 0, 0);
+    }
+    paint(&mut composed, &fill_mask, color, 0, 0);
+
+    let x = ax as i64 + ox as i64 - margin as i64;
+    let y = ay as i64 + oy as i64 - margin as i64;
+    imageops::overlay(canvas, &composed, x, y);
+    Ok(())
+}
+
+/// Draws each text layer over `img` in order.
+pub fn apply_text_layers(img: DynamicImage, layers: &[TextLayer]) -> Result<DynamicImage> {
+    if layers.is_empty() {
+        return Ok(img);
+    }
+    let has_alpha = img.color().has_alpha();
+    let mut canvas = img.to_rgba8();
+    for layer in layers {
+        draw_layer(&mut canvas, layer)?;
+    }
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    })
+}
@@ -0,0 +1,284 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+use imageproc::geometric_transformations::{rotate_about_center, rotate_about_center_no_crop, Border, Interpolation};
+use serde::{Deserialize, Serialize};
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::jxl::check_jxl_quality;
+use super::resize::{decode_data_url, encode_image};
+
+/// Where a smaller box sits inside a larger one.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Anchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Offset of the smaller box, given the free space left on each axis.
+    pub fn offset(self, free_w: u32, free_h: u32) -> (u32, u32) {
+        let (fx, fy) = match self {
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        };
+        ((free_w as f32 * fx).round() as u32, (free_h as f32 * fy).round() as u32)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum CropSettings {
+    /// Rectangle in source pixels.
+    Pixels { x: u32, y: u32, width: u32, height: u32 },
+    /// Rectangle as fractions (0.0–1.0) of the source size.
+    Normalized { x: f32, y: f32, width: f32, height: f32 },
+    /// Largest rectangle of `ratio` ("W:H", e.g. "4:5") that fits, placed by `anchor`.
+    Aspect {
+        ratio: String,
+        #[serde(default)]
+        anchor: Anchor,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateSettings {
+    /// Clockwise rotation. Multiples of 90 are lossless.
+    pub degrees: f32,
+    /// Grow the canvas so no corner is cut off by an arbitrary angle.
+    #[serde(default)]
+    pub expand: bool,
+    /// Color for uncovered corners; transparent when unset.
+    pub fill: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FlipSettings {
+    #[serde(default)]
+    pub horizontal: bool,
+    #[serde(default)]
+    pub vertical: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformResult {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+}
+
+pub fn parse_ratio(ratio: &str) -> Result<(u32, u32)> {
+    let invalid = || PixoraError::Process(format!("Invalid aspect ratio: {}", ratio));
+    let (w, h) = ratio.split_once(':').ok_or_else(invalid)?;
+    let w: u32 = w.trim().parse().map_err(|_| invalid())?;
+    let h: u32 = h.trim().parse().map_err(|_| invalid())?;
+    if w == 0 || h == 0 {
+        return Err(invalid());
+    }
+    Ok((w, h))
+}
+
+/// Largest `ratio_w:ratio_h` rectangle inside `width`×`height`, as (x, y, w, h).
+pub fn aspect_rect(width: u32, height: u32, ratio_w: u32, ratio_h: u32, anchor: Anchor) -> (u32, u32, u32, u32) {
+    let target = ratio_w as f64 / ratio_h as f64;
+    let (cw, ch) = if width as f64 / height as f64 > target {
+        (((height as f64 * target).round() as u32).clamp(1, width), height)
+    } else {
+        (width, ((width as f64 / target).round() as u32).clamp(1, height))
+    };
+    let (x, y) = anchor.offset(width - cw, height - ch);
+    (x, y, cw, ch)
+}
+
+/// Crop rectangle for `settings`, clamped to the image.
+pub fn crop_rect(width: u32, height: u32, settings: &CropSettings) -> Result<(u32, u32, u32, u32)> {
+    let (x, y, w, h) = match settings {
+        CropSettings::Pixels { x, y, width: w, height: h } => (*x, *y, *w, *h),
+        CropSettings::Normalized { x, y, width: w, height: h } => (
+            (x.clamp(0.0, 1.0) * width as f32).round() as u32,
+            (y.clamp(0.0, 1.0) * height as f32).round() as u32,
+            (w.clamp(0.0, 1.0) * width as f32).round() as u32,
+            (h.clamp(0.0, 1.0) * height as f32).round() as u32,
+        ),
+        CropSettings::Aspect { ratio, anchor } => {
+            let (rw, rh) = parse_ratio(ratio)?;
+            aspect_rect(width, height, rw, rh, *anchor)
+        }
+    };
+
+    let x = x.min(width);
+    let y = y.min(height);
+    let w = w.min(width - x);
+    let h = h.min(height - y);
+    if w == 0 || h == 0 {
+        return Err(PixoraError::Process("Crop rectangle is outside the image".to_string()));
+    }
+    Ok((x, y, w, h))
+}
+
+pub fn apply_crop(img: DynamicImage, settings: &CropSettings) -> Result<DynamicImage> {
+    let (width, height) = img.dimensions();
+    let (x, y, w, h) = crop_rect(width, height, settings)?;
+    if (x, y, w, h) == (0, 0, width, height) {
+        return Ok(img);
+    }
+    Ok(img.crop_imm(x, y, w, h))
+}
+
+pub fn apply_rotate(img: DynamicImage, settings: &RotateSettings) -> Result<DynamicImage> {
+    let degrees = settings.degrees.rem_euclid(360.0);
+    let near = |target: f32| (degrees - target).abs() < 0.01;
+
+    if near(0.0) || near(360.0) {
+        return Ok(img);
+    }
+    if near(90.0) {
+        return Ok(img.rotate90());
+    }
+    if near(180.0) {
+        return Ok(img.rotate180());
+    }
+    if near(270.0) {
+        return Ok(img.rotate270());
+    }
+
+    let fill = match settings.fill.as_deref() {
+        Some(hex) => parse_hex_color(hex)?,
+        None => Rgba([0, 0, 0, 0]),
+    };
+    let rgba = img.to_rgba8();
+    let theta = degrees.to_radians();
+    let rotated = if settings.expand {
+        rotate_about_center_no_crop(&rgba, theta, Interpolation::Bilinear, Border::Constant(fill))
+    } else {
+        rotate_about_center(&rgba, theta, Interpolation::Bilinear, Border::Constant(fill))
+    };
+    Ok(DynamicImage::ImageRgba8(rotated))
+}
+
+pub fn apply_flip(img: DynamicImage, settings: FlipSettings) -> DynamicImage {
+    let img = if settings.horizontal { img.fliph() } else { img };
+    if settings.vertical { img.flipv() } else { img }
+}
+
+/// Decodes, applies `op` and re-encodes one image for the standalone commands.
+pub fn transform_standalone<F>(data_url: String, format: Option<String>, quality: Option<u8>, op: F) -> Result<TransformResult>
+where
+    F: FnOnce(DynamicImage) -> Result<DynamicImage>,
+{
+    let (img, orig_format) = decode_data_url(&data_url)?;
+    let format = format.unwrap_or(orig_format);
+    check_jxl_quality(&format, quality)?;
+    let quality = quality.unwrap_or(85).clamp(1, 100);
+
+    let out = op(img)?;
+    let (data_url, size_bytes) = encode_image(&out, &format, quality)?;
+    Ok(TransformResult {
+        data_url,
+        width: out.width(),
+        height: out.height(),
+        size_bytes,
+    })
+}
+
+#[tauri::command]
+pub async fn crop_image(
+    data_url: String,
+    crop: CropSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<TransformResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        transform_standalone(data_url, format, quality, |img| apply_crop(img, &crop))
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn rotate_image(
+    data_url: String,
+    rotate: RotateSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<TransformResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        transform_standalone(data_url, format, quality, |img| apply_rotate(img, &rotate))
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn flip_image(
+    data_url: String,
+    flip: FlipSettings,
+    format: Option<String>,
+    quality: Option<u8>,
+) -> Result<TransformResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        transform_standalone(data_url, format, quality, |img| Ok(apply_flip(img, flip)))
+    }).await.map_err(|e| PixoraError::Process(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ratios() {
+        assert_eq!(parse_ratio("4:5").unwrap(), (4, 5));
+        assert_eq!(parse_ratio(" 16 : 9 ").unwrap(), (16, 9));
+        for ratio in ["", "4", "4:0", "0:3", "a:b", "4:5:6", "-1:2"] {
+            assert!(parse_ratio(ratio).is_err(), "{}", ratio);
+        }
+    }
+
+    #[test]
+    fn aspect_rect_fits_and_anchors() {
+        assert_eq!(aspect_rect(200, 100, 1, 1, Anchor::Center), (50, 0, 100, 100));
+        assert_eq!(aspect_rect(200, 100, 1, 1, Anchor::Left), (0, 0, 100, 100));
+        assert_eq!(aspect_rect(200, 100, 1, 1, Anchor::BottomRight), (100, 0, 100, 100));
+        assert_eq!(aspect_rect(100, 200, 2, 1, Anchor::Top), (0, 0, 100, 50));
+        assert_eq!(aspect_rect(100, 200, 2, 1, Anchor::Bottom), (0, 150, 100, 50));
+        // Extreme ratios still keep at least one pixel.
+        assert_eq!(aspect_rect(10, 10, 1000, 1, Anchor::TopLeft), (0, 0, 10, 1));
+    }
+
+    #[test]
+    fn crop_rect_clamps_to_the_image() {
+        let pixels = CropSettings::Pixels { x: 80, y: 10, width: 50, height: 20 };
+        assert_eq!(crop_rect(100, 100, &pixels).unwrap(), (80, 10, 20, 20));
+
+        let normalized = CropSettings::Normalized { x: 0.25, y: -1.0, width: 0.5, height: 2.0 };
+        assert_eq!(crop_rect(200, 100, &normalized).unwrap(), (50, 0, 100, 100));
+
+        let aspect = CropSettings::Aspect { ratio: "1:1".to_string(), anchor: Anchor::Right };
+        assert_eq!(crop_rect(300, 100, &aspect).unwrap(), (200, 0, 100, 100));
+    }
+
+    #[test]
+    fn crop_rect_rejects_empty_crops() {
+        let outside = CropSettings::Pixels { x: 100, y: 0, width: 10, height: 10 };
+        assert!(crop_rect(100, 100, &outside).is_err());
+        let empty = CropSettings::Normalized { x: 0.0, y: 0.0, width: 0.0, height: 1.0 };
+        assert!(crop_rect(100, 100, &empty).is_err());
+        let bad_ratio = CropSettings::Aspect { ratio: "wide".to_string(), anchor: Anchor::Center };
+        assert!(crop_rect(100, 100, &bad_ratio).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use image::{DynamicImage, Frame, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::color::parse_hex_color;
+use crate::error::Result;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum TrimMode {
+    /// Transparent borders when the corners are transparent, otherwise
+    /// borders matching the top-left pixel.
+    #[default]
+    Auto,
+    Transparent,
+    Color,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimSettings {
+    #[serde(default)]
+    pub mode: TrimMode,
+    /// Border color for `color` mode; the top-left pixel when unset.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Largest per-channel difference (0–255) still counted as border.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: u8,
+    /// Pixels of border kept around the content.
+    #[serde(default)]
+    pub padding: u32,
+}
+
+fn default_tolerance() -> u8 {
+    10
+}
+
+/// Area kept by a trim, in pixels of the image it was measured on.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TrimRect {
+    /// Smallest rectangle covering both.
+    pub fn union(self, other: TrimRect) -> TrimRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        TrimRect { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+fn differs(a: Rgba<u8>, b: Rgba<u8>, tolerance: u8) -> bool {
+    (0..4).any(|c| a[c].abs_diff(b[c]) > tolerance)
+}
+
+/// Bounding box of the content of `rgba`, padded and clamped to the image.
+/// An image that is all border keeps its full size.
+pub fn trim_rect(rgba: &RgbaImage, settings: &TrimSettings) -> Result<TrimRect> {
+    let (width, height) = rgba.dimensions();
+    let full = TrimRect { x: 0, y: 0, width, height };
+    let tolerance = settings.tolerance;
+
+    let corners = [(0, 0), (width - 1, 0), (0, height - 1), (width - 1, height - 1)];
+    let transparent = match settings.mode {
+        TrimMode::Transparent => true,
+        TrimMode::Color => false,
+        TrimMode::Auto => corners.iter().all(|&(x, y)| rgba.get_pixel(x, y)[3] <= tolerance),
+    };
+    let border = match settings.color.as_deref() {
+        Some(hex) if !transparent => parse_hex_color(hex)?,
+        _ => *rgba.get_pixel(0, 0),
+    };
+    let is_content = |px: &Rgba<u8>| {
+        if transparent { px[3] > tolerance } else { differs(*px, border, tolerance) }
+    };
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+    for (x, y, px) in rgba.enumerate_pixels() {
+        if is_content(px) {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    if min_x > max_x || min_y > max_y {
+        return Ok(full);
+    }
+
+    let pad = settings.padding;
+    let x = min_x.saturating_sub(pad);
+    let y = min_y.saturating_sub(pad);
+    let right = (max_x + 1 + pad).min(width);
+    let bottom = (max_y + 1 + pad).min(height);
+    Ok(TrimRect { x, y, width: right - x, height: bottom - y })
+}
+
+/// One rectangle covering the content of every frame, so the animation
+/// keeps a single size.
+pub fn frames_trim_rect(frames: &[Frame], settings: &TrimSettings) -> Result<TrimRect> {
+    let mut covered: Option<TrimRect> = None;
+    for frame in frames {
+        let rect = trim_rect(frame.buffer(), settings)?;
+        covered = Some(covered.map_or(rect, |c| c.union(rect)));
+    }
+    let (width, height) = frames.first().map(|f| f.buffer().dimensions()).unwrap_or((0, 0));
+    Ok(covered.unwrap_or(TrimRect { x: 0, y: 0, width, height }))
+}
+
+pub fn apply_trim_rect(img: DynamicImage, rect: TrimRect) -> DynamicImage {
+    if (rect.x, rect.y, rect.width, rect.height) == (0, 0, img.width(), img.height()) {
+        return img;
+    }
+    img.crop_imm(rect.x, rect.y, rect.width, rect.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: TrimMode, padding: u32) -> TrimSettings {
+        TrimSettings { mode, color: None, tolerance: default_tolerance(), padding }
+    }
+
+    fn with_block(fill: Rgba<u8>) -> RgbaImage {
+        let mut img = RgbaImage::from_pixel(10, 8, fill);
+        for (x, y) in [(3, 2), (6, 4)] {
+            img.put_pixel(x, y, Rgba([200, 30, 30, 255]));
+        }
+        img
+    }
+
+    #[test]
+    fn trims_transparent_borders() {
+        let img = with_block(Rgba([0, 0, 0, 0]));
+        let rect = trim_rect(&img, &settings(TrimMode::Auto, 0)).unwrap();
+        assert_eq!(rect, TrimRect { x: 3, y: 2, width: 4, height: 3 });
+    }
+
+    #[test]
+    fn trims_uniform_borders_with_padding() {
+        let img = with_block(Rgba([250, 250, 250, 255]));
+        let rect = trim_rect(&img, &settings(TrimMode::Auto, 1)).unwrap();
+        assert_eq!(rect, TrimRect { x: 2, y: 1, width: 6, height: 5 });
+        // Padding stops at the image edge.
+        let rect = trim_rect(&img, &settings(TrimMode::Color, 5)).unwrap();
+        assert_eq!(rect, TrimRect { x: 0, y: 0, width: 10, height: 8 });
+    }
+
+    #[test]
+    fn keeps_images_without_content() {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+        let rect = trim_rect(&img, &settings(TrimMode::Auto, 0)).unwrap();
+        assert_eq!(rect, TrimRect { x: 0, y: 0, width: 4, height: 4 });
+    }
+
+    #[test]
+    fn union_covers_both() {
+        let a = TrimRect { x: 2, y: 5, width: 3, height: 1 };
+        let b = TrimRect { x: 4, y: 1, width: 4, height: 2 };
+        assert_eq!(a.union(b), TrimRect { x: 2, y: 1, width: 6, height: 5 });
+    }
+}
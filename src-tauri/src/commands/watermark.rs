@@ -0,0 +1,208 @@
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::color::parse_hex_color;
+use crate::error::{PixoraError, Result};
+use super::resample::{resample, ResampleFilter};
+use super::text::{line_width, load_font, render_line};
+use super::transform::{apply_rotate, Anchor, RotateSettings};
+
+/// Font size used to measure text before scaling it to the target width.
+const MEASURE_PX: f32 = 100.0;
+
+type LogoKey = (PathBuf, Option<SystemTime>);
+
+/// Decoded logos, kept for the whole session so a batch reads each file
+/// once. Keyed by modification time, so edited files are re-read.
+static LOGOS: Mutex<Option<HashMap<LogoKey, Arc<DynamicImage>>>> = Mutex::new(None);
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum WatermarkContent {
+    /// A logo file, usually a PNG with transparency.
+    Image { path: String },
+    Text {
+        text: String,
+        /// TTF/OTF file; the bundled DejaVu Sans when unset.
+        #[serde(default)]
+        font_path: Option<String>,
+        /// Hex color, white by default.
+        #[serde(default)]
+        color: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkSettings {
+    pub content: WatermarkContent,
+    #[serde(default = "default_anchor")]
+    pub anchor: Anchor,
+    /// Distance from the edges, in percent of the image width.
+    #[serde(default = "default_margin")]
+    pub margin: f32,
+    /// Watermark width in percent of the image width.
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    /// 0–100.
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// Clockwise rotation in degrees.
+    #[serde(default)]
+    pub rotation: f32,
+    /// Repeat the watermark over the whole image instead of placing it once.
+    #[serde(default)]
+    pub tiled: bool,
+    /// Gap between tiles, in percent of the watermark size.
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+}
+
+fn default_anchor() -> Anchor {
+    Anchor::BottomRight
+}
+
+fn default_margin() -> f32 {
+    3.0
+}
+
+fn default_scale() -> f32 {
+    20.0
+}
+
+fn default_opacity() -> f32 {
+    70.0
+}
+
+fn default_spacing() -> f32 {
+    60.0
+}
+
+/// The watermark last drawn in one pipeline run and the image width it was
+/// built for, so the frames of an animation share it.
+#[derive(Default)]
+pub struct MarkCache {
+    last: Option<(u32, RgbaImage)>,
+}
+
+/// Returns the logo at `path` as RGBA, decoding it only the first time.
+fn load_logo(path: &str) -> Result<Arc<DynamicImage>> {
+    let path = PathBuf::from(path);
+    let modified = std::fs::metadata(&path)?.modified().ok();
+    let key = (path, modified);
+
+    if let Some(logo) = LOGOS
+        .lock()
+        .map_err(|e| PixoraError::Lock(e.to_string()))?
+        .as_ref()
+        .and_then(|cache| cache.get(&key))
+    {
+        return Ok(logo.clone());
+    }
+
+    let logo = image::open(&key.0).map_err(|e| PixoraError::Image(format!("Watermark image: {}", e)))?;
+    let logo = Arc::new(DynamicImage::ImageRgba8(logo.to_rgba8()));
+    let mut guard = LOGOS.lock().map_err(|e| PixoraError::Lock(e.to_string()))?;
+    let cache = guard.get_or_insert_with(HashMap::new);
+    cache.retain(|(p, _), _| *p != key.0);
+    cache.insert(key, logo.clone());
+    Ok(logo)
+}
+
+/// Builds the watermark at `target_w` pixels wide, before opacity and rotation.
+fn render_mark(content: &WatermarkContent, target_w: u32) -> Result<RgbaImage> {
+    match content {
+        WatermarkContent::Image { path } => {
+            let logo = load_logo(path)?;
+            let target_h = ((logo.height() as f64 * target_w as f64 / logo.width() as f64).round() as u32).max(1);
+            Ok(resample(&logo, target_w, target_h, ResampleFilter::Lanczos3)?.to_rgba8())
+        }
+        WatermarkContent::Text { text, font_path, color } => {
+            if text.trim().is_empty() {
+                return Err(PixoraError::Process("Watermark text is empty".to_string()));
+            }
+            let font = load_font(font_path.as_deref())?;
+            let color = match color.as_deref() {
+                Some(hex) => parse_hex_color(hex)?,
+                None => Rgba([255, 255, 255, 255]),
+            };
+            let measured = line_width(&font, MEASURE_PX, text).max(1.0);
+            let px = MEASURE_PX * target_w as f32 / measured;
+            Ok(render_line(&font, px, text, color))
+        }
+    }
+}
+
+/// Scaled, faded and rotated watermark for an image `width` pixels wide.
+fn build_mark(settings: &WatermarkSettings, width: u32, opacity: f32) -> Result<RgbaImage> {
+    let target_w = ((width as f32 * settings.scale.clamp(1.0, 100.0) / 100.0).round() as u32).max(1);
+    let mut mark = render_mark(&settings.content, target_w)?;
+    if opacity < 1.0 {
+        for px in mark.pixels_mut() {
+            px[3] = (px[3] as f32 * opacity).round() as u8;
+        }
+    }
+    if settings.rotation.rem_euclid(360.0) != 0.0 {
+        let rotate = RotateSettings { degrees: settings.rotation, expand: true, fill: None };
+        mark = apply_rotate(DynamicImage::ImageRgba8(mark), &rotate)?.to_rgba8();
+    }
+    Ok(mark)
+}
+
+/// Draws the watermark over `img` after resizing, so its size is relative
+/// to the final output. `marks` must only be shared by images drawn with
+/// the same `settings`.
+pub fn apply_watermark(img: DynamicImage, settings: &WatermarkSettings, marks: &mut MarkCache) -> Result<DynamicImage> {
+    let opacity = settings.opacity.clamp(0.0, 100.0) / 100.0;
+    if opacity == 0.0 {
+        return Ok(img);
+    }
+
+    let (width, height) = (img.width(), img.height());
+    let mark = match marks.last.take() {
+        Some((w, mark)) if w == width => mark,
+        _ => build_mark(settings, width, opacity)?,
+    };
+
+    let has_alpha = img.color().has_alpha();
+    let mut canvas = img.to_rgba8();
+    let (mw, mh) = (mark.width() as i64, mark.height() as i64);
+
+    if settings.tiled {
+        let gap = settings.spacing.max(0.0) / 100.0;
+        let step_x = (mw as f32 * (1.0 + gap)).round().max(1.0) as i64;
+        let step_y = (mh as f32 * (1.0 + gap)).round().max(1.0) as i64;
+        // Staggered rows, with the pattern centered on the image.
+        let origin_x = (width as i64 - mw) / 2 % step_x - step_x;
+        let origin_y = (height as i64 - mh) / 2 % step_y - step_y;
+        let mut row = 0;
+        let mut y = origin_y;
+        while y < height as i64 {
+            let shift = if row % 2 == 1 { step_x / 2 } else { 0 };
+            let mut x = origin_x - shift;
+            while x < width as i64 {
+                imageops::overlay(&mut canvas, &mark, x, y);
+                x += step_x;
+            }
+            y += step_y;
+            row += 1;
+        }
+    } else {
+        let margin = (width as f32 * settings.margin.max(0.0) / 100.0).round() as u32;
+        let free_w = width.saturating_sub(2 * margin).saturating_sub(mark.width());
+        let free_h = height.saturating_sub(2 * margin).saturating_sub(mark.height());
+        let (x, y) = settings.anchor.offset(free_w, free_h);
+        imageops::overlay(&mut canvas, &mark, (x + margin) as i64, (y + margin) as i64);
+    }
+    marks.last = Some((width, mark));
+
+    Ok(if has_alpha {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    })
+}
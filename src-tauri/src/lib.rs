@@ -1,8 +1,9 @@
 mod commands;
+pub mod color;
 pub mod error;
 pub mod state;
 
-use commands::{compress, exif, pipeline, remove_bg, resize, save, system};
+use commands::{animation, compress, deskew, exif, faces, jxl, perspective, pipeline, redact, remove_bg, resize, save, smart_crop, system, transform};
 use state::PixoraState;
 use tauri::Manager;
 
@@ -13,6 +14,7 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_shell::init())
         .setup(|app| {
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -38,7 +40,20 @@ pub fn run() {
             pipeline::delete_temp_files,
             exif::read_exif,
             exif::strip_exif,
+            jxl::transcode_jpeg_to_jxl,
+            jxl::reconstruct_jpeg,
+            animation::extract_frame,
+            animation::create_animation,
             system::get_system_info,
+            transform::crop_image,
+            transform::rotate_image,
+            transform::flip_image,
+            smart_crop::smart_crop_image,
+            redact::redact_image,
+            faces::detect_faces_in_image,
+            faces::check_face_model_exists,
+            deskew::deskew_image,
+            perspective::perspective_image,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::Destroyed = event {
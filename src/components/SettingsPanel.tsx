@@ -28,6 +28,7 @@ const FORMATS = [
   { value: "webp" as const, label: "WebP", badge: "✓", descKey: "webpDesc" as const },
   { value: "jpeg" as const, label: "JPG", badge: null, descKey: "jpgDesc" as const },
   { value: "png" as const, label: "PNG", badge: null, descKey: "pngDesc" as const },
+  { value: "jxl" as const, label: "JXL", badge: null, descKey: "jxlDesc" as const },
 ];
 
 export function SettingsPanel() {
@@ -56,7 +57,7 @@ export function SettingsPanel() {
 
       <div className="px-3 pb-8 space-y-1">
         <Section title={t.settings.format.section} badge={t.settings.format.badge} disabled={isProcessing}>
-          <div className="grid grid-cols-3 gap-1 px-1">
+          <div className="grid grid-cols-4 gap-1 px-1">
             {FORMATS.map((f) => {
               return (
                 <button
@@ -76,7 +77,7 @@ export function SettingsPanel() {
             })}
           </div>
 
-          {format !== "png" && (
+          {format !== "png" && format !== "jxl" && (
             <div className="mt-4 px-1 space-y-4">
               <div className="flex items-center justify-between">
                 <span className="text-[10px] text-slate-500 dark:text-obsidian-400 uppercase tracking-widest font-bold">
@@ -8,7 +8,9 @@ import { listen } from "@tauri-apps/api/event";
 import { useProgressStore } from "../lib/progressStore";
 import { useT } from "../lib/langStore";
 
-const ALLOWED_EXTENSIONS = ["jpg", "jpeg", "png", "webp", "gif", "tiff", "tif", "bmp"];
+// Camera RAW formats decoded by the backend (`RAW_FORMATS` in raw.rs).
+const RAW_EXTENSIONS = ["dng", "cr2", "nef", "nrw", "arw", "orf", "rw2", "raf", "pef", "srw"];
+const ALLOWED_EXTENSIONS = ["jpg", "jpeg", "png", "webp", "gif", "tiff", "tif", "bmp", "jxl", "svg", ...RAW_EXTENSIONS];
 
 export function useFileDrop() {
     const t = useT();
@@ -13,6 +13,7 @@ const en = {
       webpDesc: "Perfect balance",
       jpgDesc: "Universal",
       pngDesc: "Lossless",
+      jxlDesc: "Lossless archive",
       quality: "Quality",
       qualityLow: "Low",
       qualityMid: "Optimal (80%)",
@@ -180,6 +181,7 @@ const es: T = {
       webpDesc: "Balance perfecto",
       jpgDesc: "Universal",
       pngDesc: "Sin pérdida",
+      jxlDesc: "Archivo sin pérdida",
       quality: "Calidad",
       qualityLow: "Baja",
       qualityMid: "Óptima (80%)",
@@ -345,6 +347,7 @@ const pt: T = {
       webpDesc: "Equilíbrio perfeito",
       jpgDesc: "Universal",
       pngDesc: "Sem perdas",
+      jxlDesc: "Arquivo sem perdas",
       quality: "Qualidade",
       qualityLow: "Baixa",
       qualityMid: "Ótima (80%)",
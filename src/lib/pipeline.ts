@@ -6,6 +6,18 @@ export interface PipelineResult {
   width: number;
   height: number;
   sizeBytes: number;
+  frameCount: number;
+  trim: { x: number; y: number; width: number; height: number } | null;
+  smartCrop: {
+    x: number;
+    y: number;
+    width: number;
+    height: number;
+    focalX: number;
+    focalY: number;
+  } | null;
+  sizeWithoutDenoise: number | null;
+  deskewAngle: number | null;
 }
 
 export async function runPipeline(
@@ -16,11 +28,24 @@ export async function runPipeline(
     dataUrl: originalDataUrl,
     settings: {
       format: settings.format,
-      quality: settings.quality,
+      // JPEG XL output is lossless and rejects a quality setting.
+      quality: settings.format === "jxl" ? null : settings.quality,
       resizeEnabled: settings.resizeEnabled ?? false,
       resizeMaxPx: settings.resizeMaxPx,
       resizeCustomH: settings.resizeCustomH,
+      smartCrop: settings.smartCrop ?? null,
+      faceBlur: settings.faceBlur ?? null,
+      perspective: settings.perspective ?? null,
+      deskew: settings.deskew ?? null,
       removeBgEnabled: settings.removeBgEnabled ?? false,
+      filters: settings.filters ?? [],
+      trim: settings.trim ?? null,
+      canvas: settings.canvas ?? null,
+      textLayers: settings.textLayers ?? [],
+      watermark: settings.watermark ?? null,
+      denoise: settings.denoise ?? null,
+      flatten: settings.flatten ?? null,
+      quantize: settings.quantize ?? null,
     },
   });
 }
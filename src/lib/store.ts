@@ -11,14 +11,136 @@ export interface ImageItem {
   error?: string;
 }
 
+export type Anchor =
+  | "center" | "top" | "bottom" | "left" | "right"
+  | "topLeft" | "topRight" | "bottomLeft" | "bottomRight";
+
+export interface WatermarkSettings {
+  content:
+    | { kind: "image"; path: string }
+    | { kind: "text"; text: string; fontPath?: string | null; color?: string | null };
+  anchor: Anchor;
+  margin: number;
+  scale: number;
+  opacity: number;
+  rotation: number;
+  tiled: boolean;
+  spacing: number;
+}
+
+export interface SmartCropSettings {
+  ratio: string;
+  saliency?: "auto" | "entropy" | "edges" | "subject";
+}
+
+export interface FaceBlurSettings {
+  threshold: number;
+  padding: number;
+  effect:
+    | { kind: "blur"; strength?: number }
+    | { kind: "pixelate"; size?: number }
+    | { kind: "fill"; color?: string | null };
+  shape: "rect" | "ellipse";
+}
+
+export interface DeskewSettings {
+  maxAngle: number;
+  autoCrop: boolean;
+  fill?: string | null;
+}
+
+export interface PerspectiveSettings {
+  corners:
+    | { mode: "manual"; corners: { x: number; y: number }[] }
+    | { mode: "auto" };
+  width?: number | null;
+  height?: number | null;
+  scan?: { radius?: number; offset?: number } | null;
+}
+
+export interface TrimSettings {
+  mode: "auto" | "transparent" | "color";
+  color?: string | null;
+  tolerance: number;
+  padding: number;
+}
+
+export interface CanvasSettings {
+  pad:
+    | { mode: "size"; width: number; height: number; anchor?: Anchor }
+    | { mode: "margins"; top?: number; right?: number; bottom?: number; left?: number }
+    | null;
+  background: string | null;
+  borderWidth: number;
+  borderColor: string | null;
+  cornerRadius: number;
+  circle: boolean;
+}
+
+export interface TextLayer {
+  text: string;
+  fontPath?: string | null;
+  size?: number;
+  color?: string | null;
+  align?: "left" | "center" | "right";
+  lineSpacing?: number;
+  area?: { x: number; y: number; width: number; height: number };
+  anchor?: Anchor;
+  stroke?: { color?: string | null; width?: number } | null;
+  shadow?: { color?: string | null; offsetX?: number; offsetY?: number; blur?: number } | null;
+  background?: { color: string; padding?: number } | null;
+}
+
+export type Filter =
+  | { kind: "grayscale" }
+  | { kind: "sepia"; intensity?: number }
+  | { kind: "duotone"; shadows: string; highlights: string }
+  | { kind: "invert" }
+  | { kind: "posterize"; levels?: number }
+  | { kind: "vignette"; amount?: number; radius?: number; softness?: number }
+  | { kind: "grain"; amount?: number; seed?: number };
+
+export interface DenoiseSettings {
+  method: "bilateral" | "nonLocalMeans";
+  luma: number;
+  chroma: number;
+  reportSize: boolean;
+}
+
+export interface FlattenSettings {
+  background:
+    | { kind: "color"; color?: string | null }
+    | { kind: "blur"; radius?: number };
+}
+
+export interface QuantizeSettings {
+  palette:
+    | { kind: "auto"; colors?: number }
+    | { kind: "colors"; colors: string[] }
+    | { kind: "file"; path: string };
+  dither?: "none" | "floydSteinberg" | "ordered";
+}
+
 export interface PipelineSettings {
-  format: "jpeg" | "webp" | "png";
+  format: "jpeg" | "webp" | "png" | "jxl";
   quality: number;
   resizeEnabled: boolean;
   resizeMaxPx: number;
   resizeCustomH: number;
+  smartCrop: SmartCropSettings | null;
+  faceBlur: FaceBlurSettings | null;
+  perspective: PerspectiveSettings | null;
+  deskew: DeskewSettings | null;
   removeBgEnabled: boolean;
   stripExifEnabled: boolean;
+  filters: Filter[];
+  trim: TrimSettings | null;
+  canvas: CanvasSettings | null;
+  textLayers: TextLayer[];
+  watermark: WatermarkSettings | null;
+  denoise: DenoiseSettings | null;
+  flatten: FlattenSettings | null;
+  quantize: QuantizeSettings | null;
 }
 
 interface ImageStore {
@@ -58,8 +180,20 @@ const DEFAULT_PIPELINE: PipelineSettings = {
   resizeEnabled: false,
   resizeMaxPx: 1920,
   resizeCustomH: 0,
+  smartCrop: null,
+  faceBlur: null,
+  perspective: null,
+  deskew: null,
   removeBgEnabled: false,
   stripExifEnabled: false,
+  filters: [],
+  trim: null,
+  canvas: null,
+  textLayers: [],
+  watermark: null,
+  denoise: null,
+  flatten: null,
+  quantize: null,
 };
 
 export const useImageStore = create<ImageStore>()(
@@ -144,6 +278,18 @@ export const useImageStore = create<ImageStore>()(
           quality: s.pipeline.quality,
           resizeMaxPx: s.pipeline.resizeMaxPx,
           resizeCustomH: s.pipeline.resizeCustomH,
+          smartCrop: s.pipeline.smartCrop,
+          faceBlur: s.pipeline.faceBlur,
+          perspective: s.pipeline.perspective,
+          deskew: s.pipeline.deskew,
+          filters: s.pipeline.filters,
+          trim: s.pipeline.trim,
+          canvas: s.pipeline.canvas,
+          textLayers: s.pipeline.textLayers,
+          watermark: s.pipeline.watermark,
+          denoise: s.pipeline.denoise,
+          flatten: s.pipeline.flatten,
+          quantize: s.pipeline.quantize,
         },
       }),
       merge: (persisted: unknown, current) => ({